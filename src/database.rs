@@ -1,10 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 
-use glob::glob;
-use rusqlite::{params, Connection, Result};
+use bevy::prelude::Resource;
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+use crate::file_system::find_images;
+use crate::migrations::migrate;
+use crate::rating::{elo_update, fit_bradley_terry, glicko2_update, GlickoRating, RatingMode};
+
+// Enough for every distinct statement in this file, so none are re-prepared.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
 
 fn get_database_path() -> Result<PathBuf> {
     let exe_path = env::current_exe().expect("Failed to get the executable path");
@@ -17,84 +25,175 @@ fn get_database_path() -> Result<PathBuf> {
     Ok(db_path)
 }
 
-pub fn database_exists() -> bool {
-    let db_path = get_database_path().expect("Failed to get the database path.");
-    db_path.exists()
+/// The one connection to the database, shared by every system. Functions in this module take it as a `&Connection`, which may also be a transaction.
+#[derive(Resource)]
+pub struct TournamentStore {
+    connection: Mutex<Connection>,
 }
 
-pub(crate) fn initialize_database(image_folder_path: PathBuf) -> Result<()> {
-    let db_path = get_database_path().expect("Error getting database path.");
+impl TournamentStore {
+    /// Opens the database next to the executable in WAL mode and brings its schema up to date.
+    pub fn open() -> Result<TournamentStore> {
+        let db_path = get_database_path()?;
+        let mut connection = Connection::open(db_path)?;
 
-    // check if the database file exists and delete it if it does
-    if db_path.exists() {
-        fs::remove_file(&db_path).expect("Failed to delete existing database file");
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |row| {
+            row.get::<usize, String>(0)
+        })?;
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+        migrate(&mut connection)?;
+
+        Ok(TournamentStore {
+            connection: Mutex::new(connection),
+        })
     }
 
-    println!("Initializing database...");
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .expect("The database connection is poisoned.")
+    }
+}
 
-    // create a new SQLite database
-    let conn = Connection::open(db_path)?;
+const TOURNAMENT_IN_PROGRESS: &str = "in_progress";
+const TOURNAMENT_FINISHED: &str = "finished";
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS images (\
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,\
-                    image_path STRING,\
-                    rating INTEGER DEFAULT 0,\
-                    out INTEGER DEFAULT 0)",
-        params![],
-    )?;
+/// The `winner_id` of a drawn match. Byes and round markers also have a 0 in them, but a draw is between two real images.
+pub(crate) const DRAW: u64 = 0;
+
+/// The choices made before a tournament begins, restored when it is resumed.
+pub(crate) struct TournamentSettings {
+    pub mode: String,
+    pub speed: String,
+    pub rating_mode: String,
+    pub top_k: u64,
+    pub elo_k_factor: f64,
+}
+
+/// A tournament as listed on the main menu.
+pub(crate) struct TournamentSummary {
+    pub id: u64,
+    /// The folder chosen when the tournament was created. Images may be in its subfolders.
+    pub source_folder: String,
+    pub created_at: String,
+    pub settings: TournamentSettings,
+}
+
+fn get_tournament_summary_from_row(row: &rusqlite::Row) -> Result<TournamentSummary> {
+    Ok(TournamentSummary {
+        id: row.get(0)?,
+        source_folder: row.get(1)?,
+        created_at: row.get(2)?,
+        settings: TournamentSettings {
+            mode: row.get(3)?,
+            speed: row.get(4)?,
+            rating_mode: row.get(5)?,
+            top_k: row.get(6)?,
+            elo_k_factor: row.get(7)?,
+        },
+    })
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS matches (
-                  id INTEGER PRIMARY KEY AUTOINCREMENT,
-                  round_number INTEGER NOT NULL DEFAULT 1,
-                  participant1_id INTEGER,
-                  participant2_id INTEGER,
-                  winner_id INTEGER,
-                  FOREIGN KEY (participant1_id) REFERENCES participants(id),
-                  FOREIGN KEY (participant2_id) REFERENCES participants(id),
-                  FOREIGN KEY (winner_id) REFERENCES participants(id)
-              )",
-        params![],
+/// Every tournament that hasn't finished yet, newest first.
+pub(crate) fn get_tournaments_in_progress(conn: &Connection) -> Result<Vec<TournamentSummary>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source_folder, created_at, mode, speed, rating_mode, top_k, elo_k_factor
+         FROM tournaments
+         WHERE status = ?1
+         ORDER BY id DESC",
     )?;
 
-    let image_patterns = vec!["*.jpg", "*.jpeg", "*.png"];
+    let tournaments = stmt
+        .query_map(
+            params![TOURNAMENT_IN_PROGRESS],
+            get_tournament_summary_from_row,
+        )?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(tournaments)
+}
+
+pub(crate) fn get_tournament(conn: &Connection, tournament_id: u64) -> Result<TournamentSummary> {
+    conn.prepare_cached(
+        "SELECT id, source_folder, created_at, mode, speed, rating_mode, top_k, elo_k_factor
+         FROM tournaments
+         WHERE id = ?1",
+    )?
+    .query_row(params![tournament_id], get_tournament_summary_from_row)
+}
+
+pub(crate) fn set_tournament_finished(conn: &Connection, tournament_id: u64) -> Result<()> {
+    conn.prepare_cached("UPDATE tournaments SET status = ?1 WHERE id = ?2")?
+        .execute(params![TOURNAMENT_FINISHED, tournament_id])?;
+
+    Ok(())
+}
+
+/// Reopens a finished tournament, once its last decision has been undone.
+pub(crate) fn set_tournament_in_progress(conn: &Connection, tournament_id: u64) -> Result<()> {
+    conn.prepare_cached("UPDATE tournaments SET status = ?1 WHERE id = ?2")?
+        .execute(params![TOURNAMENT_IN_PROGRESS, tournament_id])?;
+
+    Ok(())
+}
+
+/// Creates a new tournament from every image in the folder and returns its id. Earlier tournaments are kept.
+pub(crate) fn initialize_database(
+    conn: &mut Connection,
+    image_folder_path: PathBuf,
+    settings: &TournamentSettings,
+) -> Result<u64> {
+    println!("Initializing database...");
+
     let image_folder_path = image_folder_path.to_string_lossy().to_string();
+    let created_at = Local::now().format("%Y-%m-%d %H:%M").to_string();
 
-    for pattern in image_patterns {
-        let full_pattern = format!("{}/**/{}", image_folder_path, pattern);
-        for entry in glob(&full_pattern).expect("Failed to read glob pattern.") {
-            match entry {
-                Ok(path) => {
-                    let image_path = path.to_string_lossy();
-                    let image_path = image_path.replace("\\", "/");
-
-                    conn.execute(
-                        "INSERT INTO images (image_path) VALUES (?1)",
-                        params![image_path],
-                    )?;
-                }
-                Err(e) => println!("{:?}", e),
-            }
-        }
+    let transaction = conn.transaction()?;
+
+    transaction
+        .prepare_cached(
+            "INSERT INTO tournaments
+                 (source_folder, created_at, mode, speed, rating_mode, top_k, elo_k_factor, status)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?
+        .execute(params![
+            image_folder_path,
+            created_at,
+            settings.mode,
+            settings.speed,
+            settings.rating_mode,
+            settings.top_k,
+            settings.elo_k_factor,
+            TOURNAMENT_IN_PROGRESS
+        ])?;
+    let tournament_id = transaction.last_insert_rowid() as u64;
+
+    for path in find_images(Path::new(&image_folder_path)) {
+        let image_path = path.to_string_lossy().replace("\\", "/");
+
+        transaction
+            .prepare_cached("INSERT INTO images (tournament_id, image_path) VALUES (?1, ?2)")?
+            .execute(params![tournament_id, image_path])?;
     }
 
+    transaction.commit()?;
+
     println!("Successfully initialized database...");
 
-    Ok(())
+    Ok(tournament_id)
 }
 
-pub(crate) fn get_latest_round_number() -> Result<u64> {
-    let db_path = get_database_path().expect("Error getting database path");
-    let conn = Connection::open(db_path).expect("Error opening connection");
-
-    let query = "SELECT COALESCE(MAX(round_number), 1) FROM matches".to_string();
-    conn.query_row(&query, params![], |row| row.get::<usize, i64>(0))
-        .map(|count| count as u64)
+pub(crate) fn get_latest_round_number(conn: &Connection, tournament_id: u64) -> Result<u64> {
+    conn.prepare_cached(
+        "SELECT COALESCE(MAX(round_number), 1) FROM matches WHERE tournament_id = ?1",
+    )?
+    .query_row(params![tournament_id], |row| row.get::<usize, i64>(0))
+    .map(|count| count as u64)
 }
 
-pub(crate) fn _get_total_number_of_rounds(conn: &Connection) -> Result<u64> {
-    let total_images = get_total_number_of_participants(conn)?;
+pub(crate) fn get_total_number_of_rounds(conn: &Connection, tournament_id: u64) -> Result<u64> {
+    let total_images = get_total_number_of_participants(conn, tournament_id)?;
 
     if total_images < 2 {
         return Ok(0);
@@ -102,93 +201,264 @@ pub(crate) fn _get_total_number_of_rounds(conn: &Connection) -> Result<u64> {
     Ok((total_images as f64).log2().ceil() as u64)
 }
 
-pub(crate) fn get_remaining_participants() -> Result<Vec<u64>> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let round_number = get_latest_round_number().expect("Failed to get round_number");
+pub(crate) fn get_remaining_participants(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Vec<u64>> {
+    get_remaining_participants_ordered_by(conn, tournament_id, "id")
+}
+
+/// Like `get_remaining_participants`, but ordered by wins (then Elo) so neighbours have similar scores. Ties are broken randomly.
+pub(crate) fn get_remaining_participants_by_score(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Vec<u64>> {
+    get_remaining_participants_ordered_by(conn, tournament_id, "rating DESC, elo DESC, RANDOM()")
+}
 
-    let conn = Connection::open(db_path).expect("Error opening connection");
+/// Like `get_remaining_participants`, but with the winners' bracket first and the losers' bracket after it, shuffled within each bracket.
+pub(crate) fn get_remaining_participants_by_bracket(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Vec<u64>> {
+    get_remaining_participants_ordered_by(conn, tournament_id, "losses ASC, RANDOM()")
+}
 
-    let mut sql_statement = conn.prepare(
+/// The images in the tournament that haven't played in the latest round and aren't out.
+fn get_remaining_participants_ordered_by(
+    conn: &Connection,
+    tournament_id: u64,
+    order_by: &str,
+) -> Result<Vec<u64>> {
+    let round_number =
+        get_latest_round_number(conn, tournament_id).expect("Failed to get round_number");
+
+    let query = format!(
         "SELECT id FROM images
-         WHERE id NOT IN (
-             SELECT participant1_id FROM matches WHERE round_number = ?1
+         WHERE tournament_id = ?1
+         AND id NOT IN (
+             SELECT participant1_id FROM matches WHERE tournament_id = ?1 AND round_number = ?2
              UNION ALL
-             SELECT participant2_id FROM matches WHERE round_number = ?1
-         ) 
-         AND out != 1",
-    )?;
+             SELECT participant2_id FROM matches WHERE tournament_id = ?1 AND round_number = ?2
+         )
+         AND out != 1
+         ORDER BY {}",
+        order_by
+    );
+    let mut sql_statement = conn.prepare_cached(&query)?;
 
     let participants = sql_statement
-        .query_map(params![round_number], |row| row.get::<usize, i64>(0))?
+        .query_map(params![tournament_id, round_number], |row| {
+            row.get::<usize, i64>(0)
+        })?
         .map(|result| result.unwrap() as u64)
         .collect();
 
     Ok(participants)
 }
 
-pub(crate) fn get_image_path_with_max_rating() -> Result<String> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+/// Every pair of images that has met in a match, with the smaller id first.
+pub(crate) fn get_played_pairs(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<HashSet<(u64, u64)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT participant1_id, participant2_id FROM matches
+         WHERE tournament_id = ?1 AND participant1_id != 0 AND participant2_id != 0",
+    )?;
 
-    conn.query_row(
-        "SELECT image_path FROM images ORDER BY rating DESC LIMIT 1",
-        params![],
-        |row| row.get(0),
-    )
+    let played_pairs = stmt
+        .query_map(params![tournament_id], |row| {
+            Ok((row.get::<usize, u64>(0)?, row.get::<usize, u64>(1)?))
+        })?
+        .filter_map(Result::ok)
+        .map(|(participant1, participant2)| {
+            (
+                participant1.min(participant2),
+                participant1.max(participant2),
+            )
+        })
+        .collect();
+
+    Ok(played_pairs)
+}
+
+/// Every image that hasn't been knocked out, whether or not it has played this round.
+pub(crate) fn get_images_not_out(conn: &Connection, tournament_id: u64) -> Result<Vec<u64>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id FROM images WHERE tournament_id = ?1 AND out != 1 ORDER BY id",
+    )?;
+
+    let image_ids = stmt
+        .query_map(params![tournament_id], |row| row.get::<usize, u64>(0))?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(image_ids)
 }
 
-pub(crate) fn increment_rating(image_id: u64) -> Result<()> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+pub(crate) fn get_image_paths_not_out(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<HashSet<String>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT image_path FROM images WHERE tournament_id = ?1 AND out != 1")?;
 
-    let mut rating: i32 = conn
-        .query_row(
-            "SELECT rating FROM images WHERE id = ?1",
-            params![image_id],
-            |row| row.get(0),
-        )
-        .expect("Failed to get rating");
+    let image_paths = stmt
+        .query_map(params![tournament_id], |row| row.get::<usize, String>(0))?
+        .filter_map(Result::ok)
+        .collect();
 
-    rating += 1;
+    Ok(image_paths)
+}
 
-    conn.execute(
-        "UPDATE images SET rating = ?1 WHERE id = ?2",
-        params![rating, image_id],
+/// Every match with a winner, as `(winner, loser)`.
+pub(crate) fn get_decisive_results(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT winner_id,
+                CASE WHEN winner_id = participant1_id THEN participant2_id ELSE participant1_id END
+         FROM matches
+         WHERE tournament_id = ?1
+         AND participant1_id != 0 AND participant2_id != 0 AND winner_id != 0
+         ORDER BY id",
     )?;
 
+    let results = stmt
+        .query_map(params![tournament_id], |row| {
+            Ok((row.get::<usize, u64>(0)?, row.get::<usize, u64>(1)?))
+        })?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(results)
+}
+
+/// Writes a full ordering to the `rank` column, 1 being the best.
+pub(crate) fn set_ranks(conn: &mut Connection, ordered_image_ids: &[u64]) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare_cached("UPDATE images SET rank = ?1 WHERE id = ?2")?;
+        for (index, image_id) in ordered_image_ids.iter().enumerate() {
+            stmt.execute(params![index as u64 + 1, image_id])?;
+        }
+    }
+    transaction.commit()?;
+
     Ok(())
 }
 
-pub(crate) fn get_image_path_from_database(id: u64) -> Result<PathBuf> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+/// The best ranked image, or `None` if nothing has been ranked.
+pub(crate) fn get_image_id_with_best_rank(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Option<u64>> {
+    conn.prepare_cached(
+        "SELECT id FROM images WHERE tournament_id = ?1 AND rank IS NOT NULL
+         ORDER BY rank ASC LIMIT 1",
+    )?
+    .query_row(params![tournament_id], |row| row.get(0))
+    .optional()
+}
+
+pub(crate) fn get_number_of_swiss_rounds(conn: &Connection, tournament_id: u64) -> Result<u64> {
+    get_total_number_of_rounds(conn, tournament_id)
+}
+
+/// The best rated image still in, or `None` if every image is out.
+pub(crate) fn get_image_id_with_max_score(
+    conn: &Connection,
+    tournament_id: u64,
+    rating_mode: RatingMode,
+) -> Result<Option<u64>> {
+    let query = format!(
+        "SELECT id FROM images WHERE tournament_id = ?1 AND out != 1 ORDER BY {} LIMIT 1",
+        rating_mode.order_by_expression()
+    );
+    conn.prepare_cached(&query)?
+        .query_row(params![tournament_id], |row| row.get(0))
+        .optional()
+}
 
+pub(crate) fn increment_rating(conn: &Connection, image_id: u64) -> Result<()> {
+    conn.prepare_cached("UPDATE images SET rating = rating + 1 WHERE id = ?1")?
+        .execute(params![image_id])?;
+
+    Ok(())
+}
+
+pub(crate) fn get_image_path_from_database(conn: &Connection, id: u64) -> Result<PathBuf> {
     let query = "SELECT image_path FROM images WHERE id = ?1".to_string();
-    let path: String = conn.query_row(&query, params![id], |row| row.get(0))?;
+    let path: String = conn
+        .prepare_cached(&query)?
+        .query_row(params![id], |row| row.get(0))?;
 
     Ok(PathBuf::from(path))
 }
 
-pub(crate) fn set_loser_out(image_id: u64) -> Result<()> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+pub(crate) fn set_loser_out(conn: &Connection, image_id: u64) -> Result<()> {
+    conn.prepare_cached("UPDATE images SET out = ?1 WHERE id = ?2")?
+        .execute(params![1, image_id])?;
 
-    conn.execute(
-        "UPDATE images SET out = ?1 WHERE id = ?2",
-        params![1, image_id],
-    )?;
+    Ok(())
+}
+
+/// Counts a loss against an image, setting it out once it reaches `losses_before_elimination` losses.
+pub(crate) fn add_loss(
+    conn: &Connection,
+    image_id: u64,
+    losses_before_elimination: Option<u64>,
+) -> Result<()> {
+    conn.prepare_cached(
+        "UPDATE images SET losses = losses + 1,
+                           out = CASE WHEN ?2 IS NOT NULL AND losses + 1 >= ?2 THEN 1 ELSE out END
+         WHERE id = ?1",
+    )?
+    .execute(params![image_id, losses_before_elimination])?;
 
     Ok(())
 }
 
-pub(crate) fn calculate_percentiles() -> Result<HashMap<String, f64>> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+/// Counts the images still in the winners' bracket (no losses) and the losers' bracket (one loss).
+pub(crate) fn get_bracket_sizes(conn: &Connection, tournament_id: u64) -> Result<(u64, u64)> {
+    conn.prepare_cached(
+        "SELECT COALESCE(SUM(losses = 0), 0), COALESCE(SUM(losses = 1), 0)
+         FROM images WHERE tournament_id = ?1 AND out != 1",
+    )?
+    .query_row(params![tournament_id], |row| Ok((row.get(0)?, row.get(1)?)))
+}
+
+pub(crate) fn calculate_percentiles(
+    conn: &Connection,
+    tournament_id: u64,
+    rating_mode: RatingMode,
+) -> Result<HashMap<String, f64>> {
+    calculate_percentiles_ordered_by(conn, tournament_id, &rating_mode.order_by_expression())
+}
+
+/// Percentiles from the `rank` column, for tournament modes that produce a full ordering. Unranked images come last.
+pub(crate) fn calculate_percentiles_by_rank(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<HashMap<String, f64>> {
+    calculate_percentiles_ordered_by(conn, tournament_id, "rank IS NULL, rank ASC")
+}
 
-    // retrieve all images
-    let mut stmt = conn.prepare("SELECT image_path FROM images ORDER BY rating DESC")?;
+fn calculate_percentiles_ordered_by(
+    conn: &Connection,
+    tournament_id: u64,
+    order_by: &str,
+) -> Result<HashMap<String, f64>> {
+    // retrieve all images, best first
+    let query = format!(
+        "SELECT image_path FROM images WHERE tournament_id = ?1 ORDER BY {}",
+        order_by
+    );
+    let mut stmt = conn.prepare_cached(&query)?;
     let images = stmt
-        .query_map(params![], |row| {
+        .query_map(params![tournament_id], |row| {
             let image_path: String = row.get(0)?;
             Ok(image_path)
         })?
@@ -209,15 +479,29 @@ pub(crate) fn calculate_percentiles() -> Result<HashMap<String, f64>> {
     Ok(percentiles)
 }
 
-fn get_total_number_of_participants(conn: &Connection) -> Result<u64> {
-    let total_images: u64 =
-        conn.query_row("SELECT COUNT(*) FROM images", params![], |row| row.get(0))?;
+fn get_total_number_of_participants(conn: &Connection, tournament_id: u64) -> Result<u64> {
+    let total_images: u64 = conn
+        .prepare_cached("SELECT COUNT(*) FROM images WHERE tournament_id = ?1")?
+        .query_row(params![tournament_id], |row| row.get(0))?;
 
     Ok(total_images)
 }
 
-pub(crate) fn get_number_of_matches(conn: &Connection, round_number: u64) -> Result<u64> {
-    let total_images = get_total_number_of_participants(conn)?;
+/// How many images in the tournament haven't been knocked out or rejected.
+pub(crate) fn get_number_of_participants_still_in(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<u64> {
+    conn.prepare_cached("SELECT COUNT(*) FROM images WHERE tournament_id = ?1 AND out != 1")?
+        .query_row(params![tournament_id], |row| row.get(0))
+}
+
+pub(crate) fn get_number_of_matches(
+    conn: &Connection,
+    tournament_id: u64,
+    round_number: u64,
+) -> Result<u64> {
+    let total_images = get_total_number_of_participants(conn, tournament_id)?;
 
     if round_number < 1 || total_images < 2 {
         return Ok(0);
@@ -232,32 +516,525 @@ pub(crate) fn get_number_of_matches(conn: &Connection, round_number: u64) -> Res
 
 pub(crate) fn get_total_number_of_matches_until_now(
     conn: &Connection,
+    tournament_id: u64,
     round_number: u64,
 ) -> Result<u64> {
     if round_number == 1 {
-        return get_number_of_matches(conn, round_number);
+        return get_number_of_matches(conn, tournament_id, round_number);
     }
 
-    let matches_for_current_round = get_number_of_matches(conn, round_number)?;
-    let matches_for_past_rounds = get_total_number_of_matches_until_now(conn, round_number - 1)?;
+    let matches_for_current_round = get_number_of_matches(conn, tournament_id, round_number)?;
+    let matches_for_past_rounds =
+        get_total_number_of_matches_until_now(conn, tournament_id, round_number - 1)?;
 
     Ok(matches_for_current_round + matches_for_past_rounds)
 }
 
-pub(crate) fn insert_match_into_database(
+/// Closes the round after the decision that finished it: the image left over gets a bye, recorded as a match against nobody that it wins, and a round marker starts the next round. Both carry the decision's id, so undoing the decision reopens the round.
+pub(crate) fn record_end_of_round(
+    conn: &Connection,
+    tournament_id: u64,
+    decision_id: i64,
+    round_number: u64,
+    bye: Option<u64>,
+) -> Result<()> {
+    if let Some(sole_image) = bye {
+        insert_match_row(
+            conn,
+            tournament_id,
+            Some(decision_id),
+            round_number,
+            sole_image,
+            0,
+            sole_image,
+        )?;
+    }
+
+    insert_match_row(
+        conn,
+        tournament_id,
+        Some(decision_id),
+        round_number + 1,
+        0,
+        0,
+        0,
+    )
+}
+
+/// The outcome of one displayed match: every winner beat every loser.
+pub(crate) struct MatchResult {
+    pub decision_id: i64,
+    pub round_number: u64,
+    pub winners: Vec<u64>,
+    pub losers: Vec<u64>,
+}
+
+/// Records a match result in a single transaction: a snapshot of every participant's scores, a match row per winner and loser, the winners' wins, the losers' losses and both ratings. A loser beaten by several winners only loses once. Recording the same `decision_id` again changes nothing. Returns whether the result was recorded.
+pub(crate) fn record_match_result(
+    conn: &mut Connection,
+    tournament_id: u64,
+    result: &MatchResult,
+    losses_before_elimination: Option<u64>,
+    elo_k_factor: f64,
+) -> Result<bool> {
+    let transaction = conn.transaction()?;
+
+    if is_decision_recorded(&transaction, result.decision_id)? {
+        return Ok(false);
+    }
+
+    let participants: Vec<u64> = result
+        .winners
+        .iter()
+        .chain(&result.losers)
+        .copied()
+        .collect();
+    save_image_snapshots(&transaction, result.decision_id, &participants)?;
+
+    for &winner in &result.winners {
+        for &loser in &result.losers {
+            insert_match_row(
+                &transaction,
+                tournament_id,
+                Some(result.decision_id),
+                result.round_number,
+                winner,
+                loser,
+                winner,
+            )?;
+
+            increment_rating(&transaction, winner)?;
+            update_elo_ratings(&transaction, winner, loser, winner, elo_k_factor)?;
+            update_glicko_ratings(&transaction, winner, loser, winner)?;
+        }
+    }
+
+    for &loser in &result.losers {
+        add_loss(&transaction, loser, losses_before_elimination)?;
+    }
+
+    transaction.commit()?;
+
+    Ok(true)
+}
+
+/// A decision taken back by `undo_last_match_result`.
+pub(crate) struct UndoneDecision {
+    /// The participants of the undone match, winners first.
+    pub participants: Vec<u64>,
+    /// The image given a bye when the decision closed its round.
+    pub bye: Option<u64>,
+    /// Whether the decision was the last of its round, so undoing it reopened the round.
+    pub closed_round: bool,
+}
+
+/// Reverts the most recent decision of the latest round in a single transaction: its match rows are deleted, along with the bye and round marker if it closed its round, and every participant gets back the scores it had before. Returns `None` if there is nothing in this round to undo.
+pub(crate) fn undo_last_match_result(
+    conn: &mut Connection,
+    tournament_id: u64,
+) -> Result<Option<UndoneDecision>> {
+    let transaction = conn.transaction()?;
+
+    // Decisions recorded before snapshots were kept can't be reverted.
+    let decision_id: Option<i64> = transaction
+        .prepare_cached(
+            "SELECT decision_id FROM matches
+             WHERE tournament_id = ?1
+               AND round_number = (SELECT MAX(round_number) FROM matches WHERE tournament_id = ?1)
+               AND decision_id IN (SELECT decision_id FROM image_snapshots)
+             ORDER BY id DESC LIMIT 1",
+        )?
+        .query_row(params![tournament_id], |row| row.get(0))
+        .optional()?;
+
+    let Some(decision_id) = decision_id else {
+        return Ok(None);
+    };
+
+    let mut undone_decision = UndoneDecision {
+        participants: Vec::new(),
+        bye: None,
+        closed_round: false,
+    };
+    {
+        let mut stmt = transaction.prepare_cached(
+            "SELECT participant1_id, participant2_id, winner_id FROM matches
+             WHERE decision_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![decision_id], |row| {
+            Ok((
+                row.get::<usize, u64>(0)?,
+                row.get::<usize, u64>(1)?,
+                row.get::<usize, u64>(2)?,
+            ))
+        })?;
+
+        for row in rows {
+            match row? {
+                (0, 0, _) => undone_decision.closed_round = true,
+                (sole_image, 0, winner) if winner == sole_image => {
+                    undone_decision.bye = Some(sole_image)
+                }
+                (participant1, participant2, _) => {
+                    for participant in [participant1, participant2] {
+                        // rejected images lost to nobody
+                        if participant != 0 && !undone_decision.participants.contains(&participant)
+                        {
+                            undone_decision.participants.push(participant);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    transaction
+        .prepare_cached(
+            "UPDATE images SET (rating, elo, glicko_rating, glicko_deviation, glicko_volatility, losses, out) =
+                 (SELECT rating, elo, glicko_rating, glicko_deviation, glicko_volatility, losses, out
+                  FROM image_snapshots
+                  WHERE image_snapshots.decision_id = ?1 AND image_snapshots.image_id = images.id)
+             WHERE id IN (SELECT image_id FROM image_snapshots WHERE decision_id = ?1)",
+        )?
+        .execute(params![decision_id])?;
+    transaction
+        .prepare_cached("DELETE FROM image_snapshots WHERE decision_id = ?1")?
+        .execute(params![decision_id])?;
+    transaction
+        .prepare_cached("DELETE FROM matches WHERE decision_id = ?1")?
+        .execute(params![decision_id])?;
+
+    transaction.commit()?;
+
+    Ok(Some(undone_decision))
+}
+
+/// Records a draw between every pair of participants in a single transaction, with a snapshot of their scores. Wins and losses are untouched; the Elo and Glicko-2 ratings score it as half a win. Recording the same `decision_id` again changes nothing. Returns whether the draw was recorded.
+pub(crate) fn record_draw(
+    conn: &mut Connection,
+    tournament_id: u64,
+    decision_id: i64,
+    round_number: u64,
+    participants: &[u64],
+    elo_k_factor: f64,
+) -> Result<bool> {
+    let transaction = conn.transaction()?;
+
+    if is_decision_recorded(&transaction, decision_id)? {
+        return Ok(false);
+    }
+
+    save_image_snapshots(&transaction, decision_id, participants)?;
+
+    for (index, &participant1) in participants.iter().enumerate() {
+        for &participant2 in &participants[index + 1..] {
+            insert_match_row(
+                &transaction,
+                tournament_id,
+                Some(decision_id),
+                round_number,
+                participant1,
+                participant2,
+                DRAW,
+            )?;
+
+            update_elo_ratings(&transaction, participant1, participant2, DRAW, elo_k_factor)?;
+            update_glicko_ratings(&transaction, participant1, participant2, DRAW)?;
+        }
+    }
+
+    transaction.commit()?;
+
+    Ok(true)
+}
+
+/// Rejects every participant in a single transaction: each gets a loss against nobody, recorded as a match with a participant and winner of 0, and is set out. Recording the same `decision_id` again changes nothing. Returns whether the rejection was recorded.
+pub(crate) fn record_rejection(
+    conn: &mut Connection,
+    tournament_id: u64,
+    decision_id: i64,
+    round_number: u64,
+    participants: &[u64],
+) -> Result<bool> {
+    let transaction = conn.transaction()?;
+
+    if is_decision_recorded(&transaction, decision_id)? {
+        return Ok(false);
+    }
+
+    save_image_snapshots(&transaction, decision_id, participants)?;
+
+    for &participant in participants {
+        insert_match_row(
+            &transaction,
+            tournament_id,
+            Some(decision_id),
+            round_number,
+            participant,
+            0,
+            0,
+        )?;
+
+        add_loss(&transaction, participant, None)?;
+        set_loser_out(&transaction, participant)?;
+    }
+
+    transaction.commit()?;
+
+    Ok(true)
+}
+
+/// Inserts one row into `matches`. Rows recorded as part of a decision carry its id, so they can be undone together.
+fn insert_match_row(
+    conn: &Connection,
+    tournament_id: u64,
+    decision_id: Option<i64>,
     round_number: u64,
     participant1: u64,
     participant2: u64,
     winner: u64,
 ) -> Result<()> {
-    let db_path = get_database_path().expect("Error getting database path.");
-    let conn = Connection::open(db_path).expect("Error opening connection");
+    conn.prepare_cached(
+        "INSERT INTO matches
+             (tournament_id, decision_id, round_number, participant1_id, participant2_id, winner_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        tournament_id,
+        decision_id,
+        round_number,
+        participant1,
+        participant2,
+        winner
+    ])?;
+
+    Ok(())
+}
+
+/// Sets images out as a consequence of the latest decision, in a single transaction, adding them to its snapshot so that undoing the decision brings them back.
+pub(crate) fn knock_out_after_last_decision(
+    conn: &mut Connection,
+    tournament_id: u64,
+    image_ids: &[u64],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+
+    let decision_id: Option<i64> = transaction
+        .prepare_cached(
+            "SELECT decision_id FROM matches
+             WHERE tournament_id = ?1 AND decision_id IN (SELECT decision_id FROM image_snapshots)
+             ORDER BY id DESC LIMIT 1",
+        )?
+        .query_row(params![tournament_id], |row| row.get(0))
+        .optional()?;
+
+    if let Some(decision_id) = decision_id {
+        save_image_snapshots(&transaction, decision_id, image_ids)?;
+    }
+    for &image_id in image_ids {
+        set_loser_out(&transaction, image_id)?;
+    }
+
+    transaction.commit()
+}
 
-    conn.execute(
-        "INSERT INTO matches (round_number, participant1_id, participant2_id, winner_id)
-                         VALUES (?1, ?2, ?3, ?4)",
-        params![round_number, participant1, participant2, winner],
+fn is_decision_recorded(conn: &Connection, decision_id: i64) -> Result<bool> {
+    conn.prepare_cached("SELECT EXISTS(SELECT 1 FROM matches WHERE decision_id = ?1)")?
+        .query_row(params![decision_id], |row| row.get(0))
+}
+
+/// Keeps the participants' scores from before a decision, so that it can be undone.
+fn save_image_snapshots(conn: &Connection, decision_id: i64, image_ids: &[u64]) -> Result<()> {
+    for &image_id in image_ids {
+        conn.prepare_cached(
+            "INSERT OR IGNORE INTO image_snapshots
+                 (decision_id, image_id, rating, elo, glicko_rating, glicko_deviation, glicko_volatility, losses, out)
+             SELECT ?1, id, rating, elo, glicko_rating, glicko_deviation, glicko_volatility, losses, out
+             FROM images WHERE id = ?2",
+        )?
+        .execute(params![decision_id, image_id])?;
+    }
+
+    Ok(())
+}
+
+/// A participant's score in a match: 1 for a win, 0.5 for a draw and 0 for a loss.
+fn get_match_score(participant: u64, winner: u64) -> f64 {
+    if winner == participant {
+        1.0
+    } else if winner == DRAW {
+        0.5
+    } else {
+        0.0
+    }
+}
+
+fn update_elo_ratings(
+    conn: &Connection,
+    participant1: u64,
+    participant2: u64,
+    winner: u64,
+    elo_k_factor: f64,
+) -> Result<()> {
+    let get_elo = |image_id: u64| -> Result<f64> {
+        conn.prepare_cached("SELECT elo FROM images WHERE id = ?1")?
+            .query_row(params![image_id], |row| row.get(0))
+    };
+
+    let elo_1 = get_elo(participant1)?;
+    let elo_2 = get_elo(participant2)?;
+    let score_1 = get_match_score(participant1, winner);
+
+    let (new_elo_1, new_elo_2) = elo_update(elo_1, elo_2, score_1, elo_k_factor);
+
+    conn.prepare_cached("UPDATE images SET elo = ?1 WHERE id = ?2")?
+        .execute(params![new_elo_1, participant1])?;
+    conn.prepare_cached("UPDATE images SET elo = ?1 WHERE id = ?2")?
+        .execute(params![new_elo_2, participant2])?;
+
+    Ok(())
+}
+
+fn get_glicko_rating(conn: &Connection, image_id: u64) -> Result<GlickoRating> {
+    conn.prepare_cached(
+        "SELECT glicko_rating, glicko_deviation, glicko_volatility FROM images WHERE id = ?1",
+    )?
+    .query_row(params![image_id], |row| {
+        Ok(GlickoRating {
+            rating: row.get(0)?,
+            deviation: row.get(1)?,
+            volatility: row.get(2)?,
+        })
+    })
+}
+
+/// The Glicko-2 ratings of every image still in the tournament.
+pub(crate) fn get_glicko_ratings(
+    conn: &Connection,
+    tournament_id: u64,
+) -> Result<Vec<(u64, GlickoRating)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, glicko_rating, glicko_deviation, glicko_volatility FROM images
+         WHERE tournament_id = ?1 AND out != 1",
     )?;
 
+    let glicko_ratings = stmt
+        .query_map(params![tournament_id], |row| {
+            Ok((
+                row.get::<usize, u64>(0)?,
+                GlickoRating {
+                    rating: row.get(1)?,
+                    deviation: row.get(2)?,
+                    volatility: row.get(3)?,
+                },
+            ))
+        })?
+        .filter_map(Result::ok)
+        .collect();
+
+    Ok(glicko_ratings)
+}
+
+fn set_glicko_rating(conn: &Connection, image_id: u64, glicko_rating: GlickoRating) -> Result<()> {
+    conn.prepare_cached(
+        "UPDATE images SET glicko_rating = ?1, glicko_deviation = ?2, glicko_volatility = ?3
+         WHERE id = ?4",
+    )?
+    .execute(params![
+        glicko_rating.rating,
+        glicko_rating.deviation,
+        glicko_rating.volatility,
+        image_id
+    ])?;
+
+    Ok(())
+}
+
+fn update_glicko_ratings(
+    conn: &Connection,
+    participant1: u64,
+    participant2: u64,
+    winner: u64,
+) -> Result<()> {
+    let glicko_1 = get_glicko_rating(conn, participant1)?;
+    let glicko_2 = get_glicko_rating(conn, participant2)?;
+    let score_1 = get_match_score(participant1, winner);
+
+    // both updates use the ratings from before the match
+    set_glicko_rating(
+        conn,
+        participant1,
+        glicko2_update(glicko_1, glicko_2, score_1),
+    )?;
+    set_glicko_rating(
+        conn,
+        participant2,
+        glicko2_update(glicko_2, glicko_1, 1.0 - score_1),
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn get_rating_score(
+    conn: &Connection,
+    image_id: u64,
+    rating_mode: RatingMode,
+) -> Result<f64> {
+    let query = format!(
+        "SELECT CAST({} AS REAL) FROM images WHERE id = ?1",
+        rating_mode.score_expression()
+    );
+    conn.prepare_cached(&query)?
+        .query_row(params![image_id], |row| row.get(0))
+}
+
+/// Re-fits Bradley-Terry strengths over the tournament's whole match history and writes them to the `strength` column.
+pub(crate) fn fit_bradley_terry_strengths(conn: &mut Connection, tournament_id: u64) -> Result<()> {
+    let image_ids = conn
+        .prepare("SELECT id FROM images WHERE tournament_id = ?1")?
+        .query_map(params![tournament_id], |row| row.get::<usize, u64>(0))?
+        .collect::<Result<Vec<u64>>>()?;
+
+    let indices: HashMap<u64, usize> = image_ids
+        .iter()
+        .enumerate()
+        .map(|(index, &image_id)| (image_id, index))
+        .collect();
+
+    // byes and round markers have a participant of 0 and are not comparisons; draws count half
+    let results = conn
+        .prepare(
+            "SELECT participant1_id, participant2_id, winner_id FROM matches
+             WHERE tournament_id = ?1
+             AND participant1_id != 0 AND participant2_id != 0",
+        )?
+        .query_map(params![tournament_id], |row| {
+            Ok((
+                row.get::<usize, u64>(0)?,
+                row.get::<usize, u64>(1)?,
+                row.get::<usize, u64>(2)?,
+            ))
+        })?
+        .filter_map(Result::ok)
+        .filter_map(|(participant1, participant2, winner)| {
+            let a = *indices.get(&participant1)?;
+            let b = *indices.get(&participant2)?;
+            let score_a = get_match_score(participant1, winner);
+            Some((a, b, score_a))
+        })
+        .collect::<Vec<_>>();
+
+    let strengths = fit_bradley_terry(image_ids.len(), &results);
+
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt =
+            transaction.prepare_cached("UPDATE images SET strength = ?1 WHERE id = ?2")?;
+        for (image_id, strength) in image_ids.iter().zip(strengths) {
+            stmt.execute(params![strength, image_id])?;
+        }
+    }
+    transaction.commit()?;
+
     Ok(())
 }
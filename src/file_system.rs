@@ -1,12 +1,25 @@
+use crate::raw_previews::RAW_FORMATS;
 use chrono::Local;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use rfd::FileDialog;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::Result;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// The image extensions decoded directly, matched case-insensitively, and the format each is counted under. A folder scan also picks up the `RAW_FORMATS`.
+const DECODED_FORMATS: [(&str, &str); 8] = [
+    ("jpg", "JPEG"),
+    ("jpeg", "JPEG"),
+    ("png", "PNG"),
+    ("webp", "WebP"),
+    ("bmp", "BMP"),
+    ("tif", "TIFF"),
+    ("tiff", "TIFF"),
+    ("gif", "GIF"),
+];
+
 fn get_user_home_directory() -> PathBuf {
     dirs::home_dir().expect("Could not find the home directory")
 }
@@ -35,9 +48,11 @@ pub fn create_image_directory(original_folder_name: &str) -> PathBuf {
         ))
 }
 
+/// Copies every image at or above `minimum_percentile` into the new directory, prefixed with its percentile.
 pub fn copy_images_to_directory(
     percentile_map: HashMap<String, f64>,
     new_directory: &str,
+    minimum_percentile: f64,
 ) -> Result<()> {
     if !Path::new(new_directory).exists() {
         fs::create_dir_all(new_directory)?;
@@ -46,8 +61,7 @@ pub fn copy_images_to_directory(
     percentile_map
         .par_iter()
         .try_for_each(|(image_path, &percentile)| {
-            // if in the top 85th percentile
-            if percentile >= 85f64 {
+            if percentile >= minimum_percentile {
                 // create a path
                 let path = Path::new(&image_path);
 
@@ -69,6 +83,71 @@ pub fn copy_images_to_directory(
         })
 }
 
+/// Every image in the folder and its subfolders, in path order. Each file is listed once, however it was reached; symlinked folders are not followed, so links can't make the scan loop.
+pub fn find_images(folder: &Path) -> Vec<PathBuf> {
+    let mut images = Vec::new();
+    let mut seen = HashSet::new();
+    let mut format_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut folders_to_scan = vec![folder.to_path_buf()];
+
+    while let Some(folder) = folders_to_scan.pop() {
+        let entries = match fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("Could not read {}: {}", folder.to_string_lossy(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                folders_to_scan.push(path);
+                continue;
+            }
+
+            let Some(format) = get_image_format(&path) else {
+                continue;
+            };
+
+            // a symlink to a file is fine, as long as the file is only listed once
+            if !path.is_file() {
+                continue;
+            }
+            let canonical_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if seen.insert(canonical_path) {
+                *format_counts.entry(format).or_default() += 1;
+                images.push(path);
+            }
+        }
+    }
+
+    images.sort();
+
+    let counts = format_counts
+        .iter()
+        .map(|(format, count)| format!("{} {}", format, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("Found {} images. {}", images.len(), counts);
+
+    images
+}
+
+fn get_image_format(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+
+    DECODED_FORMATS
+        .iter()
+        .chain(&RAW_FORMATS)
+        .find(|(image_extension, _)| *image_extension == extension)
+        .map(|(_, format)| *format)
+}
+
 pub fn open_folder() -> Option<PathBuf> {
     FileDialog::new().pick_folder()
 }
@@ -20,3 +20,7 @@ pub struct OpenCreatedFolderButton;
 
 #[derive(Component)]
 pub struct StartOverButton;
+
+/// Takes back the decision that ended the tournament and returns to it.
+#[derive(Component)]
+pub struct UndoLastChoiceButton;
@@ -1,8 +1,9 @@
+use crate::database::{set_tournament_in_progress, undo_last_match_result, TournamentStore};
 use crate::file_system::open_folder;
 use crate::finished::components::*;
 use crate::finished::systems::open_new_folder;
 use crate::main_menu::systems::*;
-use crate::resources::ImageFolderPath;
+use crate::resources::{CurrentTournament, ImageFolderPath};
 use crate::styles::*;
 use crate::AppState;
 use bevy::prelude::{BackgroundColor, Changed, Interaction, NextState, Query, Res, ResMut, With};
@@ -54,3 +55,38 @@ pub fn interact_with_start_over_button(
         }
     }
 }
+
+/// A misclick can end the tournament, so the deciding choice can still be taken back from here.
+pub fn interact_with_undo_last_choice_button(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<UndoLastChoiceButton>),
+    >,
+    mut app_state_next_state: ResMut<NextState<AppState>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let mut conn = tournament_store.connection();
+                let undone_decision = undo_last_match_result(&mut conn, current_tournament.id())
+                    .expect("Failed to undo the last match result");
+
+                if undone_decision.is_some() {
+                    set_tournament_in_progress(&conn, current_tournament.id())
+                        .expect("Failed to reopen the tournament.");
+                    app_state_next_state.set(AppState::Tournament);
+                } else {
+                    println!("There is nothing left to undo.");
+                }
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
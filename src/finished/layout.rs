@@ -1,13 +1,19 @@
-use crate::database::{get_image_path_from_database, get_remaining_participants};
+use crate::database::{
+    get_image_id_with_best_rank, get_image_id_with_max_score, get_image_path_from_database,
+    get_rating_score, get_remaining_participants, TournamentStore,
+};
 use crate::finished::components::*;
+use crate::rating::RatingMode;
+use crate::resources::CurrentTournament;
+use crate::speed_select::components::TournamentMode;
 use crate::styles::*;
 use crate::tournament::components::ParticipantsDeque;
+use crate::tournament::thumbnails::decode_image;
 use crate::AppState;
 use bevy::asset::AssetServer;
 use bevy::prelude::*;
 use bevy::text::BreakLineOn;
 use bevy::window::PrimaryWindow;
-use image::GenericImageView;
 
 pub fn spawn_finished_screen(
     mut commands: Commands,
@@ -15,24 +21,52 @@ pub fn spawn_finished_screen(
     window_query: Query<&Window, With<PrimaryWindow>>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
     mut next_app_state: ResMut<NextState<AppState>>,
+    rating_mode: Res<State<RatingMode>>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+    mut images: ResMut<Assets<Image>>,
 ) {
+    let tournament_id = current_tournament.id();
+    let conn = tournament_store.connection();
+
     let window: &Window = window_query.get_single().unwrap();
     let window_width = window.width();
     let window_height = window.height();
 
-    let mut participants = get_remaining_participants().unwrap();
+    let rating_mode = rating_mode.get();
+
+    // Elimination modes leave a single participant standing; otherwise the winner is the best ranked or rated.
+    let image_id_1 = if tournament_mode.get().ranks_images() {
+        get_image_id_with_best_rank(&conn, tournament_id).expect("Couldn't get image_id")
+    } else if tournament_mode.get().eliminates_losers() {
+        get_remaining_participants(&conn, tournament_id)
+            .unwrap()
+            .first()
+            .copied()
+    } else {
+        get_image_id_with_max_score(&conn, tournament_id, *rating_mode)
+            .expect("Couldn't get image_id")
+    };
+
+    // Every image may have failed to load, leaving no favourite to show.
+    let winner_1 = image_id_1.map(|image_id_1| {
+        let image_path_1 = get_image_path_from_database(&conn, image_id_1)
+            .expect("Failed to get image path from database");
+        let image_1 = decode_image(&image_path_1).expect("Failed to decode the winning image");
+        let (width_1, height_1) = (image_1.width(), image_1.height());
+        let image_aspect_ratio_1 = width_1 as f32 / height_1 as f32;
+        let target_height = window_height / 2.0;
+        let target_width = target_height * image_aspect_ratio_1;
+
+        // decoded ourselves, as the asset server can't load paths with non-ASCII characters, RAW previews or formats other than PNG and JPEG
+        let texture_handle_1 = images.add(image_1);
 
-    let image_id_1 = participants.get(0).expect("Couldn't get image_id");
-    let image_path_1 =
-        get_image_path_from_database(*image_id_1).expect("Failed to get image path from database");
-    let image_1 = image::open(&image_path_1).unwrap();
-    let (width_1, height_1) = image_1.dimensions();
-    let image_aspect_ratio_1 = width_1 as f32 / height_1 as f32;
-    let target_height = window_height / 2.0;
-    let target_width = target_height * image_aspect_ratio_1;
-    let (final_width_1, final_height_1) = (target_width, target_height);
+        let score_1 =
+            get_rating_score(&conn, image_id_1, *rating_mode).expect("Failed to get rating score");
 
-    let texture_handle_1: Handle<Image> = asset_server.load(image_path_1);
+        (texture_handle_1, target_width, target_height, score_1)
+    });
 
     commands
         .spawn((
@@ -48,7 +82,11 @@ pub fn spawn_finished_screen(
             parent.spawn(TextBundle {
                 text: Text {
                     sections: vec![TextSection::new(
-                        "Winner!",
+                        if winner_1.is_some() {
+                            "Winner!"
+                        } else {
+                            "No favourite left"
+                        },
                         TextStyle {
                             font: asset_server.load("fonts/OpenSans-SemiBold.ttf"),
                             font_size: 64.0,
@@ -61,16 +99,35 @@ pub fn spawn_finished_screen(
                 ..default()
             });
 
-            // image
-            parent.spawn(ImageBundle {
-                style: Style {
-                    width: Val::Px(final_width_1),
-                    height: Val::Px(final_height_1),
+            if let Some((texture_handle_1, final_width_1, final_height_1, score_1)) = &winner_1 {
+                // score text
+                parent.spawn(TextBundle {
+                    text: Text {
+                        sections: vec![TextSection::new(
+                            format!(
+                                "{}: {}",
+                                rating_mode.score_label(),
+                                rating_mode.format_score(*score_1)
+                            ),
+                            get_button_text_style(&asset_server),
+                        )],
+                        justify: JustifyText::Center,
+                        linebreak_behavior: BreakLineOn::NoWrap,
+                    },
                     ..default()
-                },
-                image: UiImage::new(texture_handle_1),
-                ..default()
-            });
+                });
+
+                // image
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(*final_width_1),
+                        height: Val::Px(*final_height_1),
+                        ..default()
+                    },
+                    image: UiImage::new(texture_handle_1.clone()),
+                    ..default()
+                });
+            }
 
             // horizontal flexbox
             parent
@@ -104,6 +161,30 @@ pub fn spawn_finished_screen(
                             });
                         });
 
+                    // undo button
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: BUTTON_STYLE,
+                                background_color: NORMAL_BUTTON_COLOR.into(),
+                                ..default()
+                            },
+                            UndoLastChoiceButton {},
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle {
+                                text: Text {
+                                    sections: vec![TextSection::new(
+                                        "Undo last choice",
+                                        get_button_text_style(&asset_server),
+                                    )],
+                                    justify: JustifyText::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            });
+                        });
+
                     // restart button
                     parent
                         .spawn((
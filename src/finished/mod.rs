@@ -3,9 +3,12 @@ use bevy::prelude::*;
 use crate::finished::components::{FavouritesFolderResource, FolderGeneratedState};
 use crate::finished::interactions::{
     interact_with_new_folder_button, interact_with_start_over_button,
+    interact_with_undo_last_choice_button,
 };
 use crate::finished::layout::{despawn_finished_screen, spawn_finished_screen};
-use crate::finished::systems::generate_favourites_folder;
+use crate::finished::systems::{
+    fit_bradley_terry_ratings, generate_favourites_folder, mark_tournament_finished,
+};
 use crate::AppState;
 
 mod components;
@@ -21,11 +24,20 @@ impl Plugin for FinishedPlugin {
             .init_resource::<FavouritesFolderResource>()
             .add_systems(
                 OnEnter(AppState::Finished),
-                (spawn_finished_screen, generate_favourites_folder),
+                (
+                    mark_tournament_finished,
+                    fit_bradley_terry_ratings,
+                    (spawn_finished_screen, generate_favourites_folder),
+                )
+                    .chain(),
             )
             .add_systems(
                 Update,
-                interact_with_start_over_button.run_if(in_state(AppState::Finished)),
+                (
+                    interact_with_start_over_button,
+                    interact_with_undo_last_choice_button,
+                )
+                    .run_if(in_state(AppState::Finished)),
             )
             .add_systems(
                 Update,
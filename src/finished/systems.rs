@@ -1,7 +1,9 @@
 use crate::database::*;
 use crate::file_system::*;
 use crate::finished::components::{FavouritesFolderResource, FolderGeneratedState};
-use crate::resources::ImageFolderPath;
+use crate::rating::RatingMode;
+use crate::resources::{CurrentTournament, ImageFolderPath};
+use crate::speed_select::components::TournamentMode;
 use bevy::prelude::*;
 use std::path::Path;
 use std::path::PathBuf;
@@ -11,16 +13,39 @@ pub fn generate_favourites_folder(
     image_folder_path_resource: Res<ImageFolderPath>,
     mut folder_generated_next_state: ResMut<NextState<FolderGeneratedState>>,
     mut favourites_folder_resource: ResMut<FavouritesFolderResource>,
+    rating_mode: Res<State<RatingMode>>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
 ) {
+    let tournament_id = current_tournament.id();
+    let conn = tournament_store.connection();
+
     if let Some(original_folder_name) = get_original_folder_name(&image_folder_path_resource) {
         let image_directory = create_image_directory(&original_folder_name)
             .to_string_lossy()
             .to_string();
 
-        let percentile_map =
-            calculate_percentiles().expect("Failed to calculate percentiles from database.");
+        let mut percentile_map = if tournament_mode.get().ranks_images() {
+            calculate_percentiles_by_rank(&conn, tournament_id)
+        } else {
+            calculate_percentiles(&conn, tournament_id, *rating_mode.get())
+        }
+        .expect("Failed to calculate percentiles from database.");
+
+        // Top-K mode exports exactly the K images it found; otherwise export the top 15%.
+        let minimum_percentile = match tournament_mode.get() {
+            TournamentMode::TopK => {
+                let top_k_paths = get_image_paths_not_out(&conn, tournament_id)
+                    .expect("Failed to get the top K images.");
+                percentile_map.retain(|image_path, _| top_k_paths.contains(image_path));
+                0.0
+            }
+            _ => 85.0,
+        };
 
-        copy_images_to_directory(percentile_map, &image_directory).expect("Failed to copy images.");
+        copy_images_to_directory(percentile_map, &image_directory, minimum_percentile)
+            .expect("Failed to copy images.");
 
         favourites_folder_resource.favourites_folder_path = Some(image_directory);
         folder_generated_next_state.set(FolderGeneratedState::Generated);
@@ -71,3 +96,20 @@ fn get_original_folder_name(image_folder_path_resource: &Res<ImageFolderPath>) -
     }
     None
 }
+
+pub fn mark_tournament_finished(
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    set_tournament_finished(&tournament_store.connection(), current_tournament.id())
+        .expect("Failed to mark tournament finished.");
+}
+
+/// Re-fits the Bradley-Terry strengths before anything reads the final rankings.
+pub fn fit_bradley_terry_ratings(
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    fit_bradley_terry_strengths(&mut tournament_store.connection(), current_tournament.id())
+        .expect("Failed to fit Bradley-Terry strengths.");
+}
@@ -1,6 +1,7 @@
+use crate::database::TournamentStore;
 use crate::finished::FinishedPlugin;
 use crate::main_menu::MainMenuPlugin;
-use crate::resources::{ImageFolderPath, UsedMemory};
+use crate::resources::{CurrentTournament, EloKFactor, ImageFolderPath, PrefetchWindow, TopK};
 use crate::speed_select::SpeedSelectPlugin;
 use crate::systems::*;
 use crate::tournament::TournamentPlugin;
@@ -10,6 +11,9 @@ mod database;
 mod file_system;
 mod finished;
 mod main_menu;
+mod migrations;
+mod rating;
+mod raw_previews;
 mod resources;
 mod speed_select;
 mod styles;
@@ -42,8 +46,11 @@ fn main() {
         .add_plugins(FinishedPlugin)
         .init_state::<AppState>()
         .init_resource::<ImageFolderPath>()
-        .init_resource::<UsedMemory>()
+        .init_resource::<PrefetchWindow>()
+        .init_resource::<EloKFactor>()
+        .init_resource::<TopK>()
+        .init_resource::<CurrentTournament>()
+        .insert_resource(TournamentStore::open().expect("Failed to open the database."))
         .add_systems(Startup, spawn_camera)
-        .add_systems(Update, get_used_memory_percentage)
         .run();
 }
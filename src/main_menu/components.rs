@@ -8,3 +8,12 @@ pub struct OpenFolderButton;
 
 #[derive(Component)]
 pub struct ResumePreviousButton;
+
+/// The list of unfinished tournaments, shown when the resume button is pressed.
+#[derive(Component)]
+pub struct TournamentListComponent;
+
+#[derive(Component)]
+pub struct ResumeTournamentButton {
+    pub tournament_id: u64,
+}
@@ -1,11 +1,19 @@
-use crate::database::database_exists;
+use crate::database::{get_tournament, TournamentStore};
 use crate::file_system::open_folder;
-use crate::main_menu::components::{OpenFolderButton, ResumePreviousButton};
-use crate::main_menu::systems::{get_image_folder_path_from_database, ResumedState};
-use crate::resources::ImageFolderPath;
+use crate::main_menu::components::{
+    OpenFolderButton, ResumePreviousButton, ResumeTournamentButton, TournamentListComponent,
+};
+use crate::main_menu::systems::ResumedState;
+use crate::rating::RatingMode;
+use crate::resources::{CurrentTournament, EloKFactor, ImageFolderPath, TopK};
+use crate::speed_select::components::{SpeedState, TournamentMode};
 use crate::styles::{HOVERED_BUTTON_COLOR, NORMAL_BUTTON_COLOR};
 use crate::AppState;
-use bevy::prelude::{BackgroundColor, Changed, Interaction, NextState, Query, ResMut, With};
+use bevy::prelude::{
+    BackgroundColor, Changed, Children, Display, Interaction, NextState, Query, Res, ResMut, Style,
+    With,
+};
+use std::path::PathBuf;
 
 pub fn interact_with_folder_button(
     // Interaction is provided by Bevy for buttons
@@ -14,7 +22,9 @@ pub fn interact_with_folder_button(
         (Changed<Interaction>, With<OpenFolderButton>),
     >,
     mut app_state_next_state: ResMut<NextState<AppState>>,
+    mut resumed_state_next_state: ResMut<NextState<ResumedState>>,
     mut image_folder_path_resource: ResMut<ImageFolderPath>,
+    mut current_tournament: ResMut<CurrentTournament>,
 ) {
     if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
         match *interaction {
@@ -26,6 +36,8 @@ pub fn interact_with_folder_button(
                         println!("{}", folder_path.to_string_lossy().to_string());
 
                         image_folder_path_resource.image_folder_path = Some(folder_path);
+                        current_tournament.tournament_id = None;
+                        resumed_state_next_state.set(ResumedState::New);
                         app_state_next_state.set(AppState::SpeedSelect);
                     }
                     None => {
@@ -43,23 +55,25 @@ pub fn interact_with_folder_button(
     }
 }
 
+/// Shows or hides the list of tournaments that can be resumed.
 pub fn interact_with_resume_previous_button(
     mut button_query: Query<
         (&Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<ResumePreviousButton>),
     >,
-    mut app_state_next_state: ResMut<NextState<AppState>>,
-    mut resumed_state_next_state: ResMut<NextState<ResumedState>>,
-    mut image_folder_path: ResMut<ImageFolderPath>,
+    mut tournament_list_query: Query<(&mut Style, &Children), With<TournamentListComponent>>,
 ) {
     if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
         match *interaction {
             Interaction::Pressed => {
-                if database_exists() {
-                    println!("Resume a previous tournament.");
-                    get_image_folder_path_from_database(&mut image_folder_path);
-                    resumed_state_next_state.set(ResumedState::Resumed);
-                    app_state_next_state.set(AppState::SpeedSelect);
+                if let Ok((mut style, children)) = tournament_list_query.get_single_mut() {
+                    if children.is_empty() {
+                        println!("There is no previous tournament.");
+                    } else if style.display == Display::None {
+                        style.display = Display::Flex;
+                    } else {
+                        style.display = Display::None;
+                    }
                 } else {
                     println!("There is no previous tournament.");
                 }
@@ -73,3 +87,58 @@ pub fn interact_with_resume_previous_button(
         }
     }
 }
+
+pub fn interact_with_resume_tournament_buttons(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor, &ResumeTournamentButton),
+        Changed<Interaction>,
+    >,
+    mut app_state_next_state: ResMut<NextState<AppState>>,
+    mut resumed_state_next_state: ResMut<NextState<ResumedState>>,
+    mut tournament_mode_next_state: ResMut<NextState<TournamentMode>>,
+    mut speed_state_next_state: ResMut<NextState<SpeedState>>,
+    mut rating_mode_next_state: ResMut<NextState<RatingMode>>,
+    mut top_k: ResMut<TopK>,
+    mut elo_k_factor: ResMut<EloKFactor>,
+    mut image_folder_path: ResMut<ImageFolderPath>,
+    mut current_tournament: ResMut<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    for (interaction, mut background_color, resume_tournament_button) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let tournament = get_tournament(
+                    &tournament_store.connection(),
+                    resume_tournament_button.tournament_id,
+                )
+                .expect("Failed to get the tournament from the database.");
+                println!("Resume the tournament in {}.", tournament.source_folder);
+
+                let settings = &tournament.settings;
+                if let Some(tournament_mode) = TournamentMode::from_key(&settings.mode) {
+                    tournament_mode_next_state.set(tournament_mode);
+                }
+                if let Some(speed_state) = SpeedState::from_key(&settings.speed) {
+                    speed_state_next_state.set(speed_state);
+                }
+                if let Some(rating_mode) = RatingMode::from_key(&settings.rating_mode) {
+                    rating_mode_next_state.set(rating_mode);
+                }
+                top_k.0 = settings.top_k as usize;
+                elo_k_factor.0 = settings.elo_k_factor;
+
+                current_tournament.tournament_id = Some(tournament.id);
+                image_folder_path.image_folder_path =
+                    Some(PathBuf::from(&tournament.source_folder));
+                resumed_state_next_state.set(ResumedState::Resumed);
+                app_state_next_state.set(AppState::SpeedSelect);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
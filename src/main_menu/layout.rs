@@ -1,16 +1,24 @@
+use crate::database::{get_tournaments_in_progress, TournamentStore, TournamentSummary};
 use crate::main_menu::components::*;
-use crate::styles::get_button_text_style;
-use crate::styles::{BUTTON_STYLE, NORMAL_BUTTON_COLOR};
+use crate::speed_select::components::TournamentMode;
+use crate::styles::{get_button_text_style, get_list_button_text_style};
+use crate::styles::{BUTTON_STYLE, LIST_BUTTON_STYLE, NORMAL_BUTTON_COLOR};
 use bevy::prelude::*;
 use bevy::text::BreakLineOn;
 use bevy::window::PrimaryWindow;
+use std::path::Path;
 
 pub fn spawn_main_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     window_query: Query<&Window, With<PrimaryWindow>>,
+    tournament_store: Res<TournamentStore>,
 ) {
-    let main_menu_entity = build_main_menu(&mut commands, &asset_server, &window_query);
+    let tournaments = get_tournaments_in_progress(&tournament_store.connection())
+        .expect("Failed to get the tournaments in progress.");
+
+    let main_menu_entity =
+        build_main_menu(&mut commands, &asset_server, &window_query, &tournaments);
 }
 
 pub fn despawn_main_menu(
@@ -26,6 +34,7 @@ pub fn build_main_menu(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     window_query: &Query<&Window, With<PrimaryWindow>>,
+    tournaments: &[TournamentSummary],
 ) -> Entity {
     let window: &Window = window_query.get_single().unwrap();
 
@@ -112,8 +121,69 @@ pub fn build_main_menu(
                         ..default()
                     });
                 });
+
+            // tournament list, hidden until the resume button is pressed
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(8.0),
+                            ..default()
+                        },
+                        background_color: Color::WHITE.into(),
+                        ..default()
+                    },
+                    TournamentListComponent {},
+                ))
+                .with_children(|parent| {
+                    for tournament in tournaments {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: LIST_BUTTON_STYLE,
+                                    background_color: NORMAL_BUTTON_COLOR.into(),
+                                    ..default()
+                                },
+                                ResumeTournamentButton {
+                                    tournament_id: tournament.id,
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: Text {
+                                        sections: vec![TextSection::new(
+                                            get_tournament_label(tournament),
+                                            get_list_button_text_style(&asset_server),
+                                        )],
+                                        justify: JustifyText::Center,
+                                        linebreak_behavior: BreakLineOn::NoWrap,
+                                    },
+                                    ..default()
+                                });
+                            });
+                    }
+                });
         })
         .id();
 
     main_menu_entity
 }
+
+/// Describes a tournament by its folder, mode and start time.
+fn get_tournament_label(tournament: &TournamentSummary) -> String {
+    let folder_name = Path::new(&tournament.source_folder)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| tournament.source_folder.clone());
+    let mode_label = TournamentMode::from_key(&tournament.settings.mode)
+        .map(|mode| mode.label())
+        .unwrap_or("Unknown mode");
+
+    format!(
+        "{} | {} | {}",
+        folder_name, mode_label, tournament.created_at
+    )
+}
@@ -5,6 +5,7 @@ pub mod systems;
 
 use crate::main_menu::interactions::{
     interact_with_folder_button, interact_with_resume_previous_button,
+    interact_with_resume_tournament_buttons,
 };
 use crate::main_menu::layout::{despawn_main_menu, spawn_main_menu};
 use crate::main_menu::systems::ResumedState;
@@ -24,12 +25,13 @@ impl Plugin for MainMenuPlugin {
                 (
                     interact_with_folder_button,
                     interact_with_resume_previous_button,
+                    interact_with_resume_tournament_buttons,
                 )
                     .run_if(in_state(MainMenu)),
             )
             .add_systems(OnExit(AppState::MainMenu), despawn_main_menu)
             .add_systems(
-                OnEnter(AppState::SpeedSelect),
+                OnExit(AppState::SpeedSelect),
                 initialize_database_if_image_folder_path.run_if(in_state(ResumedState::New)),
             );
     }
@@ -1,7 +1,8 @@
-use crate::database::{get_image_path_with_max_rating, initialize_database};
-use crate::resources::ImageFolderPath;
+use crate::database::{initialize_database, TournamentSettings, TournamentStore};
+use crate::rating::RatingMode;
+use crate::resources::{CurrentTournament, EloKFactor, ImageFolderPath, TopK};
+use crate::speed_select::components::{SpeedState, TournamentMode};
 use bevy::prelude::*;
-use std::path::Path;
 
 #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
 pub enum ResumedState {
@@ -10,19 +11,29 @@ pub enum ResumedState {
     Resumed,
 }
 
-pub fn initialize_database_if_image_folder_path(image_folder_path: Res<ImageFolderPath>) {
+/// Starts a new tournament in the database once its settings have been chosen.
+pub fn initialize_database_if_image_folder_path(
+    image_folder_path: Res<ImageFolderPath>,
+    tournament_mode: Res<State<TournamentMode>>,
+    speed_state: Res<State<SpeedState>>,
+    rating_mode: Res<State<RatingMode>>,
+    top_k: Res<TopK>,
+    elo_k_factor: Res<EloKFactor>,
+    mut current_tournament: ResMut<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
     if let Some(path) = &image_folder_path.image_folder_path {
-        initialize_database(path.clone())
-            .expect("Something went wrong when initializing the database.");
-    }
-}
+        let settings = TournamentSettings {
+            mode: tournament_mode.get().key().to_string(),
+            speed: speed_state.get().key().to_string(),
+            rating_mode: rating_mode.get().key().to_string(),
+            top_k: top_k.0 as u64,
+            elo_k_factor: elo_k_factor.0,
+        };
 
-pub fn get_image_folder_path_from_database(mut image_folder_path: &mut ResMut<ImageFolderPath>) {
-    let image_folder_path_max_rating =
-        get_image_path_with_max_rating().expect("Failed to get image path with max rating.");
-    let path = Path::new(&image_folder_path_max_rating);
-    let directory_path = path.parent();
-
-    let image_folder_path_option = directory_path.map(|path| path.to_path_buf());
-    image_folder_path.image_folder_path = image_folder_path_option;
+        let tournament_id =
+            initialize_database(&mut tournament_store.connection(), path.clone(), &settings)
+                .expect("Something went wrong when initializing the database.");
+        current_tournament.tournament_id = Some(tournament_id);
+    }
 }
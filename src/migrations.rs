@@ -0,0 +1,486 @@
+use chrono::Local;
+use rusqlite::{ffi, params, Connection, Error, Result};
+
+use crate::rating::{
+    BRADLEY_TERRY_INITIAL_STRENGTH, ELO_DEFAULT_K_FACTOR, ELO_INITIAL_RATING,
+    GLICKO_INITIAL_DEVIATION, GLICKO_INITIAL_RATING, GLICKO_INITIAL_VOLATILITY,
+};
+
+/// Each migration upgrades the schema by one version, so the schema version is the number of migrations applied. Only ever append to this list: databases in the wild have already run the earlier entries.
+const MIGRATIONS: [fn(&Connection) -> Result<()>; 7] = [
+    create_initial_tables,
+    add_rating_columns,
+    add_tournaments,
+    add_indices,
+    add_decision_ids,
+    add_image_snapshots,
+    add_elo_k_factor,
+];
+
+/// Brings the database up to the latest schema. Databases from builds without a `schema_version` table are treated as version 0; every migration only adds what is missing, so they are upgraded in place. Fails on a database from a newer build.
+pub(crate) fn migrate(conn: &mut Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)",
+        params![],
+    )?;
+
+    let version = get_schema_version(conn)?;
+
+    // Writing to a schema this build doesn't know could corrupt it, so a newer database isn't opened at all.
+    if version > MIGRATIONS.len() {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_MISMATCH),
+            Some(format!(
+                "The database has schema version {}, but this build only knows up to version {}.",
+                version,
+                MIGRATIONS.len()
+            )),
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let new_version = index + 1;
+        println!(
+            "Migrating the database to schema version {}...",
+            new_version
+        );
+
+        let transaction = conn.transaction()?;
+        migration(&transaction)?;
+        transaction.execute("DELETE FROM schema_version", params![])?;
+        transaction.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            params![new_version as u64],
+        )?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+fn get_schema_version(conn: &Connection) -> Result<usize> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        params![],
+        |row| row.get::<usize, u64>(0),
+    )
+    .map(|version| version as usize)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map(params![], |row| row.get::<usize, String>(1))?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    Ok(columns.iter().any(|name| name == column))
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            params![],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Version 1: the single-tournament schema of the first release.
+fn create_initial_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS images (
+                  id INTEGER PRIMARY KEY AUTOINCREMENT,
+                  image_path STRING,
+                  rating INTEGER DEFAULT 0,
+                  out INTEGER DEFAULT 0
+              )",
+        params![],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS matches (
+                  id INTEGER PRIMARY KEY AUTOINCREMENT,
+                  round_number INTEGER NOT NULL DEFAULT 1,
+                  participant1_id INTEGER,
+                  participant2_id INTEGER,
+                  winner_id INTEGER,
+                  FOREIGN KEY (participant1_id) REFERENCES participants(id),
+                  FOREIGN KEY (participant2_id) REFERENCES participants(id),
+                  FOREIGN KEY (winner_id) REFERENCES participants(id)
+              )",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Version 2: Elo, Glicko-2 and Bradley-Terry ratings, losses and ranks.
+fn add_rating_columns(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "images",
+        "elo",
+        &format!("REAL DEFAULT {:.1}", ELO_INITIAL_RATING),
+    )?;
+    add_column_if_missing(
+        conn,
+        "images",
+        "glicko_rating",
+        &format!("REAL DEFAULT {:.1}", GLICKO_INITIAL_RATING),
+    )?;
+    add_column_if_missing(
+        conn,
+        "images",
+        "glicko_deviation",
+        &format!("REAL DEFAULT {:.1}", GLICKO_INITIAL_DEVIATION),
+    )?;
+    add_column_if_missing(
+        conn,
+        "images",
+        "glicko_volatility",
+        &format!("REAL DEFAULT {}", GLICKO_INITIAL_VOLATILITY),
+    )?;
+    add_column_if_missing(
+        conn,
+        "images",
+        "strength",
+        &format!("REAL DEFAULT {:.1}", BRADLEY_TERRY_INITIAL_STRENGTH),
+    )?;
+    add_column_if_missing(conn, "images", "losses", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "images", "rank", "INTEGER")?;
+
+    Ok(())
+}
+
+/// Version 3: several tournaments per database. Images and matches from before this version are moved into a tournament of their own.
+fn add_tournaments(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tournaments (
+                  id INTEGER PRIMARY KEY AUTOINCREMENT,
+                  source_folder STRING NOT NULL,
+                  created_at STRING NOT NULL,
+                  mode STRING NOT NULL
+              )",
+        params![],
+    )?;
+
+    add_column_if_missing(
+        conn,
+        "tournaments",
+        "speed",
+        "STRING NOT NULL DEFAULT 'normal'",
+    )?;
+    add_column_if_missing(
+        conn,
+        "tournaments",
+        "rating_mode",
+        "STRING NOT NULL DEFAULT 'wins'",
+    )?;
+    add_column_if_missing(conn, "tournaments", "top_k", "INTEGER NOT NULL DEFAULT 20")?;
+    add_column_if_missing(
+        conn,
+        "tournaments",
+        "status",
+        "STRING NOT NULL DEFAULT 'in_progress'",
+    )?;
+    add_column_if_missing(
+        conn,
+        "images",
+        "tournament_id",
+        "INTEGER REFERENCES tournaments(id)",
+    )?;
+    add_column_if_missing(
+        conn,
+        "matches",
+        "tournament_id",
+        "INTEGER REFERENCES tournaments(id)",
+    )?;
+
+    let image_paths = conn
+        .prepare("SELECT image_path FROM images WHERE tournament_id IS NULL")?
+        .query_map(params![], |row| row.get::<usize, String>(0))?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    if image_paths.is_empty() {
+        return Ok(());
+    }
+
+    // Older builds played a single-elimination tournament ranked by wins, and didn't record which folder was opened.
+    let created_at = Local::now().format("%Y-%m-%d %H:%M").to_string();
+    conn.execute(
+        "INSERT INTO tournaments (source_folder, created_at, mode, speed, rating_mode, top_k, status)
+                         VALUES (?1, ?2, 'elimination', 'normal', 'wins', 20, 'in_progress')",
+        params![get_common_folder(&image_paths), created_at],
+    )?;
+    let tournament_id = conn.last_insert_rowid();
+
+    conn.execute(
+        "UPDATE images SET tournament_id = ?1 WHERE tournament_id IS NULL",
+        params![tournament_id],
+    )?;
+    conn.execute(
+        "UPDATE matches SET tournament_id = ?1 WHERE tournament_id IS NULL",
+        params![tournament_id],
+    )?;
+
+    Ok(())
+}
+
+/// Version 4: indices for the per-tournament queries.
+fn add_indices(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_tournament_id ON images (tournament_id, out)",
+        params![],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS matches_tournament_id_round_number
+             ON matches (tournament_id, round_number)",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Version 5: the decision each match row was recorded by. Rows from before this version, byes and round markers have none.
+fn add_decision_ids(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "matches", "decision_id", "INTEGER")?;
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS matches_decision_id
+             ON matches (decision_id, participant1_id, participant2_id)
+             WHERE decision_id IS NOT NULL",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Version 6: the scores each image had before a decision was recorded, so the decision can be undone.
+fn add_image_snapshots(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_snapshots (
+                  decision_id INTEGER NOT NULL,
+                  image_id INTEGER NOT NULL REFERENCES images(id),
+                  rating INTEGER,
+                  elo REAL,
+                  glicko_rating REAL,
+                  glicko_deviation REAL,
+                  glicko_volatility REAL,
+                  losses INTEGER,
+                  out INTEGER,
+                  PRIMARY KEY (decision_id, image_id)
+              )",
+        params![],
+    )?;
+
+    Ok(())
+}
+
+/// Version 7: the Elo K-factor chosen for each tournament. Earlier tournaments were played with the default.
+fn add_elo_k_factor(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "tournaments",
+        "elo_k_factor",
+        &format!("REAL NOT NULL DEFAULT {:.1}", ELO_DEFAULT_K_FACTOR),
+    )?;
+
+    Ok(())
+}
+
+/// The deepest folder containing every image. Image paths are stored with `/` separators.
+fn get_common_folder(image_paths: &[String]) -> String {
+    let mut common: Vec<&str> = image_paths[0].split('/').collect();
+    // the last component is the file name
+    common.pop();
+
+    for image_path in &image_paths[1..] {
+        let folders: Vec<&str> = image_path.split('/').collect();
+        let shared = common
+            .iter()
+            .zip(folders[..folders.len() - 1].iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        common.truncate(shared);
+    }
+
+    common.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database as the first release left it: one tournament's images and matches, without a schema version.
+    fn open_baseline_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE images (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 image_path STRING,
+                 rating INTEGER DEFAULT 0,
+                 out INTEGER DEFAULT 0);
+             CREATE TABLE matches (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 round_number INTEGER NOT NULL DEFAULT 1,
+                 participant1_id INTEGER,
+                 participant2_id INTEGER,
+                 winner_id INTEGER);
+             INSERT INTO images (image_path, rating, out) VALUES
+                 ('C:/Photos/Trip/a.jpg', 2, 0),
+                 ('C:/Photos/Trip/b.jpg', 1, 1),
+                 ('C:/Photos/Trip/Day 2/c.png', 0, 1);
+             INSERT INTO matches (round_number, participant1_id, participant2_id, winner_id) VALUES
+                 (1, 1, 2, 1),
+                 (1, 3, 0, 3),
+                 (2, 0, 0, 0),
+                 (2, 1, 3, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn migrates_a_baseline_database_into_a_tournament() {
+        let mut conn = open_baseline_database();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(get_schema_version(&conn).unwrap(), MIGRATIONS.len());
+
+        let (tournament_id, source_folder, mode, rating_mode, status): (
+            i64,
+            String,
+            String,
+            String,
+            String,
+        ) = conn
+            .query_row(
+                "SELECT id, source_folder, mode, rating_mode, status FROM tournaments",
+                params![],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(source_folder, "C:/Photos/Trip");
+        assert_eq!(mode, "elimination");
+        assert_eq!(rating_mode, "wins");
+        assert_eq!(status, "in_progress");
+
+        let elo_k_factor: f64 = conn
+            .query_row(
+                "SELECT elo_k_factor FROM tournaments WHERE id = ?1",
+                params![tournament_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(elo_k_factor, ELO_DEFAULT_K_FACTOR);
+
+        let images: Vec<(String, u64, u64, i64, f64)> = conn
+            .prepare("SELECT image_path, rating, out, tournament_id, elo FROM images ORDER BY id")
+            .unwrap()
+            .query_map(params![], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            images,
+            vec![
+                (
+                    "C:/Photos/Trip/a.jpg".to_string(),
+                    2,
+                    0,
+                    tournament_id,
+                    ELO_INITIAL_RATING
+                ),
+                (
+                    "C:/Photos/Trip/b.jpg".to_string(),
+                    1,
+                    1,
+                    tournament_id,
+                    ELO_INITIAL_RATING
+                ),
+                (
+                    "C:/Photos/Trip/Day 2/c.png".to_string(),
+                    0,
+                    1,
+                    tournament_id,
+                    ELO_INITIAL_RATING
+                ),
+            ]
+        );
+
+        let matches_in_tournament: u64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM matches WHERE tournament_id = ?1 AND decision_id IS NULL",
+                params![tournament_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(matches_in_tournament, 4);
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let mut conn = open_baseline_database();
+
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        let tournaments: u64 = conn
+            .query_row("SELECT COUNT(*) FROM tournaments", params![], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(tournaments, 1);
+        assert_eq!(get_schema_version(&conn).unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_build() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "UPDATE schema_version SET version = ?1",
+            params![MIGRATIONS.len() as u64 + 1],
+        )
+        .unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn common_folder_is_the_deepest_shared_folder() {
+        let image_paths = [
+            "/home/me/Pictures/2023/a.jpg".to_string(),
+            "/home/me/Pictures/2024/summer/b.jpg".to_string(),
+            "/home/me/Pictures/c.jpg".to_string(),
+        ];
+
+        assert_eq!(get_common_folder(&image_paths), "/home/me/Pictures");
+    }
+}
@@ -0,0 +1,403 @@
+use bevy::prelude::States;
+use std::f64::consts::PI;
+
+pub const ELO_INITIAL_RATING: f64 = 1500.0;
+pub const ELO_DEFAULT_K_FACTOR: f64 = 32.0;
+pub const ELO_MIN_K_FACTOR: f64 = 4.0;
+
+pub const GLICKO_INITIAL_RATING: f64 = 1500.0;
+pub const GLICKO_INITIAL_DEVIATION: f64 = 350.0;
+pub const GLICKO_INITIAL_VOLATILITY: f64 = 0.06;
+
+// Conversion factor between the Glicko and Glicko-2 scales.
+const GLICKO_SCALE: f64 = 173.7178;
+// Constrains how quickly the volatility can change. Glickman suggests 0.3 to 1.2.
+const GLICKO_TAU: f64 = 0.5;
+const GLICKO_CONVERGENCE_TOLERANCE: f64 = 0.000001;
+
+pub const BRADLEY_TERRY_INITIAL_STRENGTH: f64 = 1.0;
+const BRADLEY_TERRY_MAX_ITERATIONS: usize = 1000;
+const BRADLEY_TERRY_CONVERGENCE_TOLERANCE: f64 = 0.000000001;
+
+/// The score used to rank images when the favourites folder is generated.
+#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum RatingMode {
+    Wins,
+    #[default]
+    Elo,
+    /// Glicko-2 rating minus twice the rating deviation, so rarely-seen images aren't ranked as if their rating were certain.
+    GlickoConservative,
+    /// Bradley-Terry strengths fitted to every match once the tournament is over.
+    BradleyTerry,
+}
+
+impl RatingMode {
+    /// The SQL expression for an image's score under this rating. Higher is better.
+    pub fn score_expression(&self) -> &'static str {
+        match self {
+            RatingMode::Wins => "rating",
+            RatingMode::Elo => "elo",
+            RatingMode::GlickoConservative => "glicko_rating - 2.0 * glicko_deviation",
+            RatingMode::BradleyTerry => "strength",
+        }
+    }
+
+    /// The SQL expression used to order images by this rating, best first.
+    pub fn order_by_expression(&self) -> String {
+        format!("{} DESC", self.score_expression())
+    }
+
+    /// How the rating mode is stored in the `tournaments` table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            RatingMode::Wins => "wins",
+            RatingMode::Elo => "elo",
+            RatingMode::GlickoConservative => "glicko_conservative",
+            RatingMode::BradleyTerry => "bradley_terry",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<RatingMode> {
+        match key {
+            "wins" => Some(RatingMode::Wins),
+            "elo" => Some(RatingMode::Elo),
+            "glicko_conservative" => Some(RatingMode::GlickoConservative),
+            "bradley_terry" => Some(RatingMode::BradleyTerry),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RatingMode::Wins => "Wins",
+            RatingMode::Elo => "Elo",
+            RatingMode::GlickoConservative => "Glicko-2",
+            RatingMode::BradleyTerry => "Bradley-Terry",
+        }
+    }
+
+    /// A description of the score, as shown on the finished screen.
+    pub fn score_label(&self) -> &'static str {
+        match self {
+            RatingMode::Wins => "Wins",
+            RatingMode::Elo => "Elo rating",
+            RatingMode::GlickoConservative => "Glicko-2 rating - 2 RD",
+            RatingMode::BradleyTerry => "Bradley-Terry strength",
+        }
+    }
+
+    pub fn format_score(&self, score: f64) -> String {
+        match self {
+            RatingMode::BradleyTerry => format!("{:.3}", score),
+            _ => format!("{:.0}", score),
+        }
+    }
+
+    /// The mode the rating button cycles to next.
+    pub fn next(&self) -> RatingMode {
+        match self {
+            RatingMode::Wins => RatingMode::Elo,
+            RatingMode::Elo => RatingMode::GlickoConservative,
+            RatingMode::GlickoConservative => RatingMode::BradleyTerry,
+            RatingMode::BradleyTerry => RatingMode::Wins,
+        }
+    }
+}
+
+/// The expected score (between 0 and 1) of a player rated `rating` against one rated `opponent_rating`.
+pub fn elo_expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+/// Returns both players' new ratings after a match. `score_a` is 1.0 if `a` won, 0.0 if it lost.
+pub fn elo_update(rating_a: f64, rating_b: f64, score_a: f64, k_factor: f64) -> (f64, f64) {
+    let expected_a = elo_expected_score(rating_a, rating_b);
+    let delta = k_factor * (score_a - expected_a);
+
+    (rating_a + delta, rating_b - delta)
+}
+
+/// A Glicko-2 rating, stored on the original Glicko scale (a new image is 1500 ± 350).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlickoRating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for GlickoRating {
+    fn default() -> Self {
+        GlickoRating {
+            rating: GLICKO_INITIAL_RATING,
+            deviation: GLICKO_INITIAL_DEVIATION,
+            volatility: GLICKO_INITIAL_VOLATILITY,
+        }
+    }
+}
+
+impl GlickoRating {
+    fn mu(&self) -> f64 {
+        (self.rating - GLICKO_INITIAL_RATING) / GLICKO_SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / GLICKO_SCALE
+    }
+}
+
+fn glicko_g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+fn glicko_expected_score(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1.0 / (1.0 + (-glicko_g(opponent_phi) * (mu - opponent_mu)).exp())
+}
+
+/// How much a match between `a` and `b` is expected to tell us: highest when the outcome is a coin flip between images with uncertain ratings.
+pub fn glicko_match_information(a: GlickoRating, b: GlickoRating) -> f64 {
+    let combined_phi = (a.phi() * a.phi() + b.phi() * b.phi()).sqrt();
+    let expected = glicko_expected_score(a.mu(), b.mu(), combined_phi);
+
+    expected * (1.0 - expected) * combined_phi * combined_phi
+}
+
+/// Returns the player's new Glicko-2 rating after a single match against `opponent`, treating the match as its own rating period. `score` is 1.0 for a win and 0.0 for a loss.
+pub fn glicko2_update(player: GlickoRating, opponent: GlickoRating, score: f64) -> GlickoRating {
+    glicko2_rating_period(player, &[(opponent, score)])
+}
+
+/// Returns the player's new Glicko-2 rating after a rating period of `results`, each an opponent and the player's score against it. This is the procedure of Glickman's "Example of the Glicko-2 system".
+fn glicko2_rating_period(player: GlickoRating, results: &[(GlickoRating, f64)]) -> GlickoRating {
+    let mu = player.mu();
+    let phi = player.phi();
+    let sigma = player.volatility;
+
+    let mut information = 0.0;
+    let mut improvement = 0.0;
+    for &(opponent, score) in results {
+        let opponent_phi = opponent.phi();
+        let g = glicko_g(opponent_phi);
+        let expected = glicko_expected_score(mu, opponent.mu(), opponent_phi);
+
+        information += g * g * expected * (1.0 - expected);
+        improvement += g * (score - expected);
+    }
+
+    // estimated variance of the player's rating based on these matches alone
+    let v = 1.0 / information;
+    // estimated improvement in rating
+    let delta = v * improvement;
+
+    // find the new volatility with the Illinois algorithm (step 5 of Glickman's paper)
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denominator = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * denominator * denominator)
+            - (x - a) / (GLICKO_TAU * GLICKO_TAU)
+    };
+
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * GLICKO_TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * GLICKO_TAU
+    };
+
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > GLICKO_CONVERGENCE_TOLERANCE {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let new_sigma = (big_a / 2.0).exp();
+
+    let phi_star = (phi * phi + new_sigma * new_sigma).sqrt();
+    let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi * new_phi * improvement;
+
+    GlickoRating {
+        rating: new_mu * GLICKO_SCALE + GLICKO_INITIAL_RATING,
+        deviation: new_phi * GLICKO_SCALE,
+        volatility: new_sigma,
+    }
+}
+
+/// Fits Bradley-Terry strengths to a set of results with Hunter's MM algorithm. Each result is `(a, b, score_a)`, where `a` and `b` index into the returned strengths and `score_a` is 1.0 if `a` won.
+///
+/// Every player also gets one virtual draw against a reference player of strength 1.0, which keeps players who never won (or never played) at a finite, comparable strength.
+pub fn fit_bradley_terry(number_of_players: usize, results: &[(usize, usize, f64)]) -> Vec<f64> {
+    let mut wins = vec![0.5; number_of_players];
+    let mut opponents: Vec<Vec<usize>> = vec![Vec::new(); number_of_players];
+
+    for &(a, b, score_a) in results {
+        wins[a] += score_a;
+        wins[b] += 1.0 - score_a;
+        opponents[a].push(b);
+        opponents[b].push(a);
+    }
+
+    let mut strengths = vec![BRADLEY_TERRY_INITIAL_STRENGTH; number_of_players];
+
+    for _ in 0..BRADLEY_TERRY_MAX_ITERATIONS {
+        let mut largest_change: f64 = 0.0;
+
+        let new_strengths: Vec<f64> = (0..number_of_players)
+            .map(|i| {
+                let mut denominator = 1.0 / (strengths[i] + BRADLEY_TERRY_INITIAL_STRENGTH);
+                for &j in &opponents[i] {
+                    denominator += 1.0 / (strengths[i] + strengths[j]);
+                }
+                wins[i] / denominator
+            })
+            .collect();
+
+        for (old, new) in strengths.iter().zip(new_strengths.iter()) {
+            largest_change = largest_change.max((new - old).abs() / old);
+        }
+
+        strengths = new_strengths;
+
+        if largest_change < BRADLEY_TERRY_CONVERGENCE_TOLERANCE {
+            break;
+        }
+    }
+
+    strengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glicko_rating(rating: f64, deviation: f64) -> GlickoRating {
+        GlickoRating {
+            rating,
+            deviation,
+            volatility: GLICKO_INITIAL_VOLATILITY,
+        }
+    }
+
+    #[test]
+    fn glicko2_matches_glickmans_worked_example() {
+        let player = glicko_rating(1500.0, 200.0);
+        let results = [
+            (glicko_rating(1400.0, 30.0), 1.0),
+            (glicko_rating(1550.0, 100.0), 0.0),
+            (glicko_rating(1700.0, 300.0), 0.0),
+        ];
+
+        let updated = glicko2_rating_period(player, &results);
+
+        assert!((updated.rating - 1464.06).abs() < 0.01, "{:?}", updated);
+        assert!((updated.deviation - 151.52).abs() < 0.01, "{:?}", updated);
+        assert!(
+            (updated.volatility - 0.05999).abs() < 0.00001,
+            "{:?}",
+            updated
+        );
+    }
+
+    #[test]
+    fn glicko2_winner_gains_what_loser_loses_between_equals() {
+        let winner = glicko2_update(GlickoRating::default(), GlickoRating::default(), 1.0);
+        let loser = glicko2_update(GlickoRating::default(), GlickoRating::default(), 0.0);
+
+        assert!(winner.rating > GLICKO_INITIAL_RATING);
+        assert!(
+            (winner.rating - GLICKO_INITIAL_RATING + loser.rating - GLICKO_INITIAL_RATING).abs()
+                < 1e-9
+        );
+        assert!(winner.deviation < GLICKO_INITIAL_DEVIATION);
+    }
+
+    /// Each player's strength should satisfy its MM fixed point: its wins, including the virtual half win, equal its expected wins.
+    fn assert_bradley_terry_converged(strengths: &[f64], results: &[(usize, usize, f64)]) {
+        for (i, &strength) in strengths.iter().enumerate() {
+            let mut wins = 0.5;
+            let mut expected_wins = strength / (strength + BRADLEY_TERRY_INITIAL_STRENGTH);
+            for &(a, b, score_a) in results {
+                if a == i {
+                    wins += score_a;
+                    expected_wins += strength / (strength + strengths[b]);
+                } else if b == i {
+                    wins += 1.0 - score_a;
+                    expected_wins += strength / (strength + strengths[a]);
+                }
+            }
+            assert!(
+                (wins - expected_wins).abs() < 1e-6,
+                "player {}: {:?}",
+                i,
+                strengths
+            );
+        }
+    }
+
+    #[test]
+    fn bradley_terry_converges_on_known_results() {
+        let results = [
+            (0, 1, 1.0),
+            (0, 1, 1.0),
+            (1, 0, 1.0),
+            (1, 2, 1.0),
+            (1, 2, 1.0),
+            (2, 1, 1.0),
+            (0, 2, 1.0),
+        ];
+
+        let strengths = fit_bradley_terry(3, &results);
+
+        assert!(
+            strengths[0] > strengths[1] && strengths[1] > strengths[2],
+            "{:?}",
+            strengths
+        );
+        assert_bradley_terry_converged(&strengths, &results);
+    }
+
+    #[test]
+    fn bradley_terry_keeps_an_unbeaten_image_finite() {
+        let results = [(0, 1, 1.0), (0, 1, 1.0), (0, 2, 1.0)];
+
+        let strengths = fit_bradley_terry(4, &results);
+
+        assert!(strengths
+            .iter()
+            .all(|strength| strength.is_finite() && *strength > 0.0));
+        assert!(strengths[0] > strengths[1] && strengths[0] > strengths[2]);
+        // an image that never played only has its virtual draw
+        assert!((strengths[3] - BRADLEY_TERRY_INITIAL_STRENGTH).abs() < 1e-6);
+        assert_bradley_terry_converged(&strengths, &results);
+    }
+
+    #[test]
+    fn bradley_terry_scores_a_draw_as_equal_strength() {
+        let strengths = fit_bradley_terry(2, &[(0, 1, 0.5)]);
+
+        assert!((strengths[0] - strengths[1]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn glicko_match_information_prefers_close_uncertain_matches() {
+        let uncertain = GlickoRating::default();
+        let certain = glicko_rating(1500.0, 50.0);
+        let far_away = glicko_rating(2300.0, 350.0);
+
+        let close_uncertain = glicko_match_information(uncertain, uncertain);
+        assert!(close_uncertain > glicko_match_information(certain, certain));
+        assert!(close_uncertain > glicko_match_information(uncertain, far_away));
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+/// Camera RAW formats built on TIFF, which are ranked by the JPEG preview the camera embeds in them, by extension and with the name they are counted under in a folder scan.
+pub const RAW_FORMATS: [(&str, &str); 4] = [
+    ("cr2", "CR2"),
+    ("nef", "NEF"),
+    ("arw", "ARW"),
+    ("dng", "DNG"),
+];
+
+// Past this many IFDs the file is taken to be malformed rather than walked forever.
+const MAXIMUM_IFDS: usize = 64;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+
+/// TIFF compression values for JPEG data.
+const COMPRESSION_OLD_JPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+
+pub fn is_raw_file(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| {
+            RAW_FORMATS
+                .iter()
+                .any(|(raw_extension, _)| *raw_extension == extension)
+        })
+}
+
+/// The largest JPEG preview embedded in a RAW file, if it has one that an ordinary JPEG decoder can read.
+pub fn read_raw_preview(path: &Path) -> Result<Option<Vec<u8>>> {
+    let data = fs::read(path)?;
+
+    Ok(find_raw_preview(&data))
+}
+
+fn find_raw_preview(data: &[u8]) -> Option<Vec<u8>> {
+    TiffReader::new(data).and_then(|tiff| {
+        tiff.find_jpeg_candidates()
+            .into_iter()
+            .filter_map(|(offset, length)| data.get(offset..offset.checked_add(length)?))
+            .filter(|candidate| is_baseline_or_progressive_jpeg(candidate))
+            .max_by_key(|candidate| candidate.len())
+            .map(<[u8]>::to_vec)
+    })
+}
+
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<TiffReader<'a>> {
+        let little_endian = match data.get(0..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+
+        let tiff = TiffReader {
+            data,
+            little_endian,
+        };
+        (tiff.read_u16(2)? == 42).then_some(tiff)
+    }
+
+    fn read_u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// The values of an IFD entry of SHORT or LONG type. Four bytes or less are stored in the entry itself, anything longer elsewhere in the file.
+    fn read_entry_values(&self, entry_offset: usize) -> Vec<u32> {
+        let field_type = self.read_u16(entry_offset + 2).unwrap_or(0);
+        let count = self.read_u32(entry_offset + 4).unwrap_or(0) as usize;
+
+        let value_size = match field_type {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Vec::new(),
+        };
+
+        let values_offset = if count.saturating_mul(value_size) <= 4 {
+            entry_offset + 8
+        } else {
+            match self.read_u32(entry_offset + 8) {
+                Some(offset) => offset as usize,
+                None => return Vec::new(),
+            }
+        };
+
+        (0..count.min(MAXIMUM_IFDS))
+            .filter_map(|index| {
+                let offset = values_offset + index * value_size;
+                if value_size == 2 {
+                    self.read_u16(offset).map(u32::from)
+                } else {
+                    self.read_u32(offset)
+                }
+            })
+            .collect()
+    }
+
+    /// Walks every IFD, including sub-IFDs, and returns the `(offset, length)` of each JPEG stream it points at. The full-size RAW data is skipped.
+    fn find_jpeg_candidates(&self) -> Vec<(usize, usize)> {
+        let mut candidates = Vec::new();
+        let mut to_visit: Vec<usize> = self
+            .read_u32(4)
+            .map(|offset| offset as usize)
+            .into_iter()
+            .collect();
+        let mut visited = HashSet::new();
+
+        while let Some(ifd_offset) = to_visit.pop() {
+            if ifd_offset == 0 || !visited.insert(ifd_offset) || visited.len() > MAXIMUM_IFDS {
+                continue;
+            }
+
+            let Some(number_of_entries) = self.read_u16(ifd_offset) else {
+                continue;
+            };
+
+            let mut new_subfile_type = None;
+            let mut compression = None;
+            let mut strip_offsets = Vec::new();
+            let mut strip_byte_counts = Vec::new();
+            let mut jpeg_offset = None;
+            let mut jpeg_length = None;
+
+            for index in 0..number_of_entries as usize {
+                let entry_offset = ifd_offset + 2 + index * 12;
+                let Some(tag) = self.read_u16(entry_offset) else {
+                    break;
+                };
+                let values = self.read_entry_values(entry_offset);
+
+                match tag {
+                    TAG_NEW_SUBFILE_TYPE => new_subfile_type = values.first().copied(),
+                    TAG_COMPRESSION => compression = values.first().copied(),
+                    TAG_STRIP_OFFSETS => strip_offsets = values,
+                    TAG_STRIP_BYTE_COUNTS => strip_byte_counts = values,
+                    TAG_SUB_IFDS => {
+                        to_visit.extend(values.into_iter().map(|offset| offset as usize))
+                    }
+                    TAG_JPEG_INTERCHANGE_FORMAT => jpeg_offset = values.first().copied(),
+                    TAG_JPEG_INTERCHANGE_FORMAT_LENGTH => jpeg_length = values.first().copied(),
+                    _ => {}
+                }
+            }
+
+            if let (Some(offset), Some(length)) = (jpeg_offset, jpeg_length) {
+                candidates.push((offset as usize, length as usize));
+            }
+
+            // DNG marks its full-size RAW data with a subfile type of 0; that is never a viewable JPEG.
+            let is_jpeg = matches!(compression, Some(COMPRESSION_OLD_JPEG | COMPRESSION_JPEG));
+            if is_jpeg && new_subfile_type != Some(0) && strip_offsets.len() == 1 {
+                if let Some(&length) = strip_byte_counts.first() {
+                    candidates.push((strip_offsets[0] as usize, length as usize));
+                }
+            }
+
+            let next_ifd_offset = ifd_offset + 2 + number_of_entries as usize * 12;
+            if let Some(next_ifd) = self.read_u32(next_ifd_offset) {
+                to_visit.push(next_ifd as usize);
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Whether a JPEG stream is one the `image` crate can decode. RAW files also hold their sensor data as lossless JPEG, which it can't.
+fn is_baseline_or_progressive_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+
+    let mut offset = 2;
+    while let (Some(&0xFF), Some(&marker)) = (data.get(offset), data.get(offset + 1)) {
+        match marker {
+            // start of frame: baseline, extended sequential or progressive
+            0xC0..=0xC2 => return true,
+            // any other start of frame, such as lossless
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return false,
+            // start of scan or end of image before any frame
+            0xDA | 0xD9 => return false,
+            // fill bytes
+            0xFF => offset += 1,
+            _ => {
+                let Some(length) = data
+                    .get(offset + 2..offset + 4)
+                    .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+                else {
+                    return false;
+                };
+                offset += 2 + length;
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD_TYPE_LONG: u16 = 4;
+    /// The smallest stream that passes as a baseline JPEG: start of image, an empty start of frame and end of image.
+    const BASELINE_JPEG: [u8; 8] = [0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x02, 0xFF, 0xD9];
+
+    /// A little-endian TIFF with one IFD, holding the given `(tag, value)` entries as LONGs and pointing at `next_ifd`, followed by a baseline JPEG.
+    fn build_tiff(entries: &[(u16, u32)], next_ifd: u32) -> Vec<u8> {
+        let mut data = b"II".to_vec();
+        data.extend(42u16.to_le_bytes());
+        data.extend(8u32.to_le_bytes());
+
+        data.extend((entries.len() as u16).to_le_bytes());
+        for &(tag, value) in entries {
+            data.extend(tag.to_le_bytes());
+            data.extend(FIELD_TYPE_LONG.to_le_bytes());
+            data.extend(1u32.to_le_bytes());
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(next_ifd.to_le_bytes());
+
+        data.extend(BASELINE_JPEG);
+        data
+    }
+
+    /// Where `build_tiff` puts the JPEG.
+    fn jpeg_offset(number_of_entries: usize) -> u32 {
+        (8 + 2 + 12 * number_of_entries + 4) as u32
+    }
+
+    fn build_tiff_with_preview(next_ifd: u32) -> Vec<u8> {
+        build_tiff(
+            &[
+                (TAG_JPEG_INTERCHANGE_FORMAT, jpeg_offset(2)),
+                (
+                    TAG_JPEG_INTERCHANGE_FORMAT_LENGTH,
+                    BASELINE_JPEG.len() as u32,
+                ),
+            ],
+            next_ifd,
+        )
+    }
+
+    #[test]
+    fn finds_the_embedded_preview() {
+        let data = build_tiff_with_preview(0);
+
+        assert_eq!(find_raw_preview(&data), Some(BASELINE_JPEG.to_vec()));
+    }
+
+    #[test]
+    fn truncated_files_have_no_preview() {
+        let data = build_tiff_with_preview(0);
+
+        for length in 0..data.len() {
+            assert_eq!(find_raw_preview(&data[..length]), None, "{} bytes", length);
+        }
+    }
+
+    #[test]
+    fn offsets_past_the_end_are_ignored() {
+        let past_the_end = build_tiff(
+            &[
+                (TAG_JPEG_INTERCHANGE_FORMAT, u32::MAX - 4),
+                (TAG_JPEG_INTERCHANGE_FORMAT_LENGTH, 1000),
+                (TAG_SUB_IFDS, u32::MAX),
+            ],
+            u32::MAX,
+        );
+        assert_eq!(find_raw_preview(&past_the_end), None);
+
+        let mut first_ifd_past_the_end = build_tiff_with_preview(0);
+        first_ifd_past_the_end[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(find_raw_preview(&first_ifd_past_the_end), None);
+    }
+
+    #[test]
+    fn ifd_loops_end() {
+        // the IFD chain and its sub-IFDs point back at the first IFD
+        let chain_loop = build_tiff_with_preview(8);
+        assert_eq!(find_raw_preview(&chain_loop), Some(BASELINE_JPEG.to_vec()));
+
+        let sub_ifd_loop = build_tiff(&[(TAG_SUB_IFDS, 8)], 8);
+        assert_eq!(find_raw_preview(&sub_ifd_loop), None);
+    }
+
+    #[test]
+    fn value_counts_are_bounded() {
+        let mut data = build_tiff(&[(TAG_SUB_IFDS, 0)], 0);
+        // claim the sub-IFD entry holds u32::MAX offsets
+        data[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let tiff = TiffReader::new(&data).unwrap();
+        assert!(tiff.read_entry_values(10).len() <= MAXIMUM_IFDS);
+        assert_eq!(find_raw_preview(&data), None);
+    }
+
+    #[test]
+    fn only_baseline_and_progressive_jpegs_are_previews() {
+        assert!(is_baseline_or_progressive_jpeg(&BASELINE_JPEG));
+        assert!(is_baseline_or_progressive_jpeg(&[0xFF, 0xD8, 0xFF, 0xC2]));
+        // lossless, as used for the sensor data
+        assert!(!is_baseline_or_progressive_jpeg(&[0xFF, 0xD8, 0xFF, 0xC3]));
+        // a segment whose length runs past the end
+        assert!(!is_baseline_or_progressive_jpeg(&[
+            0xFF, 0xD8, 0xFF, 0xE1, 0xFF, 0xFF
+        ]));
+        assert!(!is_baseline_or_progressive_jpeg(&[0xFF, 0xD8]));
+        assert!(!is_baseline_or_progressive_jpeg(&[]));
+    }
+}
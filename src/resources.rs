@@ -1,3 +1,4 @@
+use crate::rating::ELO_DEFAULT_K_FACTOR;
 use bevy::prelude::*;
 use std::path::PathBuf;
 
@@ -14,11 +15,51 @@ impl Default for ImageFolderPath {
     }
 }
 
+/// How far ahead thumbnails are loaded: the next `matches` matches, as long as they take up less than `megabytes` of memory.
 #[derive(Resource)]
-pub struct UsedMemory(pub f64);
+pub struct PrefetchWindow {
+    pub matches: usize,
+    pub megabytes: usize,
+}
+
+impl Default for PrefetchWindow {
+    fn default() -> Self {
+        PrefetchWindow {
+            matches: 4,
+            megabytes: 512,
+        }
+    }
+}
+
+/// The K-factor used when updating Elo ratings: how far a single match can move a rating.
+#[derive(Resource)]
+pub struct EloKFactor(pub f64);
 
-impl Default for UsedMemory {
+impl Default for EloKFactor {
     fn default() -> Self {
-        UsedMemory(0.0)
+        EloKFactor(ELO_DEFAULT_K_FACTOR)
+    }
+}
+
+/// How many favourites to find in top-K mode.
+#[derive(Resource)]
+pub struct TopK(pub usize);
+
+impl Default for TopK {
+    fn default() -> Self {
+        TopK(20)
+    }
+}
+
+/// The tournament being played, as its id in the `tournaments` table.
+#[derive(Resource, Default)]
+pub struct CurrentTournament {
+    pub tournament_id: Option<u64>,
+}
+
+impl CurrentTournament {
+    pub fn id(&self) -> u64 {
+        self.tournament_id
+            .expect("No tournament has been started or resumed.")
     }
 }
@@ -13,6 +13,42 @@ pub enum SpeedSelectButton {
     FastButton,
 }
 
+#[derive(Component)]
+pub struct TournamentModeButton;
+
+#[derive(Component)]
+pub struct TournamentModeButtonText;
+
+#[derive(Component)]
+pub struct TopKSelectComponent;
+
+/// Changes K by `step` when pressed.
+#[derive(Component)]
+pub struct TopKButton {
+    pub step: i64,
+}
+
+#[derive(Component)]
+pub struct TopKText;
+
+#[derive(Component)]
+pub struct RatingModeButton;
+
+#[derive(Component)]
+pub struct RatingModeButtonText;
+
+#[derive(Component)]
+pub struct EloKFactorSelectComponent;
+
+/// Changes the Elo K-factor by `step` when pressed.
+#[derive(Component)]
+pub struct EloKFactorButton {
+    pub step: f64,
+}
+
+#[derive(Component)]
+pub struct EloKFactorText;
+
 #[derive(Component)]
 pub struct SlowButton;
 
@@ -29,3 +65,142 @@ pub enum SpeedState {
     Normal,
     Fast,
 }
+
+impl SpeedState {
+    /// How the speed is stored in the `tournaments` table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            SpeedState::Slow => "slow",
+            SpeedState::Normal => "normal",
+            SpeedState::Fast => "fast",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<SpeedState> {
+        match key {
+            "slow" => Some(SpeedState::Slow),
+            "normal" => Some(SpeedState::Normal),
+            "fast" => Some(SpeedState::Fast),
+            _ => None,
+        }
+    }
+}
+
+/// How participants are scheduled against each other.
+#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum TournamentMode {
+    /// Single elimination: an image is out after its first loss.
+    #[default]
+    Elimination,
+    /// An image drops to the losers' bracket after its first loss and is out after its second.
+    DoubleElimination,
+    /// Images with similar scores are paired each round and nobody is eliminated, so every image ends up ranked.
+    Swiss,
+    /// Repeatedly schedules the matches expected to be most informative, until every rating is confident.
+    Adaptive,
+    /// Stops as soon as the best K images are known, without ordering them against each other.
+    TopK,
+    /// An interactive binary insertion sort: every image gets a strict rank.
+    MergeSort,
+    /// Every pair is compared once. Meant for small sets.
+    RoundRobin,
+}
+
+impl TournamentMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TournamentMode::Elimination => "Elimination",
+            TournamentMode::DoubleElimination => "Double elimination",
+            TournamentMode::Swiss => "Swiss",
+            TournamentMode::Adaptive => "Adaptive",
+            TournamentMode::TopK => "Top K",
+            TournamentMode::MergeSort => "Full sort",
+            TournamentMode::RoundRobin => "Round robin",
+        }
+    }
+
+    /// How the mode is stored in the `tournaments` table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            TournamentMode::Elimination => "elimination",
+            TournamentMode::DoubleElimination => "double_elimination",
+            TournamentMode::Swiss => "swiss",
+            TournamentMode::Adaptive => "adaptive",
+            TournamentMode::TopK => "top_k",
+            TournamentMode::MergeSort => "merge_sort",
+            TournamentMode::RoundRobin => "round_robin",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<TournamentMode> {
+        match key {
+            "elimination" => Some(TournamentMode::Elimination),
+            "double_elimination" => Some(TournamentMode::DoubleElimination),
+            "swiss" => Some(TournamentMode::Swiss),
+            "adaptive" => Some(TournamentMode::Adaptive),
+            "top_k" => Some(TournamentMode::TopK),
+            "merge_sort" => Some(TournamentMode::MergeSort),
+            "round_robin" => Some(TournamentMode::RoundRobin),
+            _ => None,
+        }
+    }
+
+    /// The mode the tournament mode button cycles to next.
+    pub fn next(&self) -> TournamentMode {
+        match self {
+            TournamentMode::Elimination => TournamentMode::DoubleElimination,
+            TournamentMode::DoubleElimination => TournamentMode::Swiss,
+            TournamentMode::Swiss => TournamentMode::Adaptive,
+            TournamentMode::Adaptive => TournamentMode::TopK,
+            TournamentMode::TopK => TournamentMode::MergeSort,
+            TournamentMode::MergeSort => TournamentMode::RoundRobin,
+            TournamentMode::RoundRobin => TournamentMode::Elimination,
+        }
+    }
+
+    /// How many losses knock an image out of the tournament, if any.
+    pub fn losses_before_elimination(&self) -> Option<u64> {
+        match self {
+            TournamentMode::Elimination => Some(1),
+            TournamentMode::DoubleElimination => Some(2),
+            TournamentMode::Swiss
+            | TournamentMode::Adaptive
+            | TournamentMode::TopK
+            | TournamentMode::MergeSort
+            | TournamentMode::RoundRobin => None,
+        }
+    }
+
+    /// Whether losing a match can knock an image out of the tournament.
+    pub fn eliminates_losers(&self) -> bool {
+        self.losses_before_elimination().is_some()
+    }
+
+    /// Whether Fast speed may show more than two images per match.
+    pub fn allows_large_matches(&self) -> bool {
+        match self {
+            TournamentMode::Elimination
+            | TournamentMode::DoubleElimination
+            | TournamentMode::TopK => true,
+            TournamentMode::Swiss
+            | TournamentMode::Adaptive
+            | TournamentMode::MergeSort
+            | TournamentMode::RoundRobin => false,
+        }
+    }
+
+    /// Whether a match can be called a draw. The interactive sort and top K order images by their decisive results, so they need a winner for every comparison.
+    pub fn allows_draws(&self) -> bool {
+        !matches!(self, TournamentMode::MergeSort | TournamentMode::TopK)
+    }
+
+    /// Whether the schedule is played in rounds. Other modes schedule one match at a time from the history so far.
+    pub fn uses_rounds(&self) -> bool {
+        !matches!(self, TournamentMode::MergeSort)
+    }
+
+    /// Whether the tournament writes a full ordering to the `rank` column.
+    pub fn ranks_images(&self) -> bool {
+        matches!(self, TournamentMode::MergeSort | TournamentMode::RoundRobin)
+    }
+}
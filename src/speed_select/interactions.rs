@@ -1,3 +1,5 @@
+use crate::rating::{RatingMode, ELO_MIN_K_FACTOR};
+use crate::resources::{EloKFactor, TopK};
 use crate::speed_select::components::*;
 use crate::styles::*;
 use crate::AppState;
@@ -85,3 +87,194 @@ pub fn colour_the_border_if_selected(
         }
     }
 }
+
+pub fn interact_with_tournament_mode_button(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<TournamentModeButton>),
+    >,
+    tournament_mode: Res<State<TournamentMode>>,
+    mut tournament_mode_next_state: ResMut<NextState<TournamentMode>>,
+) {
+    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let next_tournament_mode = tournament_mode.get().next();
+                println!("Tournament mode: {}", next_tournament_mode.label());
+                tournament_mode_next_state.set(next_tournament_mode);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+pub fn update_tournament_mode_button_text(
+    tournament_mode: Res<State<TournamentMode>>,
+    mut text_query: Query<&mut Text, With<TournamentModeButtonText>>,
+) {
+    if tournament_mode.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = get_tournament_mode_button_label(tournament_mode.get());
+        }
+    }
+}
+
+pub fn get_tournament_mode_button_label(tournament_mode: &TournamentMode) -> String {
+    format!("Mode: {}", tournament_mode.label())
+}
+
+pub fn interact_with_top_k_buttons(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor, &TopKButton),
+        (Changed<Interaction>, With<TopKButton>),
+    >,
+    mut top_k: ResMut<TopK>,
+) {
+    for (interaction, mut background_color, top_k_button) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                top_k.0 = (top_k.0 as i64 + top_k_button.step).max(1) as usize;
+                println!("Top K: {}", top_k.0);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+pub fn update_top_k_text(top_k: Res<TopK>, mut text_query: Query<&mut Text, With<TopKText>>) {
+    if top_k.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = get_top_k_label(&top_k);
+        }
+    }
+}
+
+pub fn get_top_k_label(top_k: &TopK) -> String {
+    format!("Top {}", top_k.0)
+}
+
+/// The K selector is only shown while top-K mode is selected.
+pub fn show_top_k_select_if_selected(
+    tournament_mode: Res<State<TournamentMode>>,
+    mut top_k_select_query: Query<&mut Style, With<TopKSelectComponent>>,
+) {
+    if tournament_mode.is_changed() {
+        for mut style in top_k_select_query.iter_mut() {
+            style.display = get_top_k_select_display(tournament_mode.get());
+        }
+    }
+}
+
+pub fn get_top_k_select_display(tournament_mode: &TournamentMode) -> Display {
+    match tournament_mode {
+        TournamentMode::TopK => Display::Flex,
+        _ => Display::None,
+    }
+}
+
+pub fn interact_with_rating_mode_button(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<RatingModeButton>),
+    >,
+    rating_mode: Res<State<RatingMode>>,
+    mut rating_mode_next_state: ResMut<NextState<RatingMode>>,
+) {
+    if let Ok((interaction, mut background_color)) = button_query.get_single_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let next_rating_mode = rating_mode.get().next();
+                println!("Rating mode: {}", next_rating_mode.label());
+                rating_mode_next_state.set(next_rating_mode);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+pub fn update_rating_mode_button_text(
+    rating_mode: Res<State<RatingMode>>,
+    mut text_query: Query<&mut Text, With<RatingModeButtonText>>,
+) {
+    if rating_mode.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = get_rating_mode_button_label(rating_mode.get());
+        }
+    }
+}
+
+pub fn get_rating_mode_button_label(rating_mode: &RatingMode) -> String {
+    format!("Ranking: {}", rating_mode.label())
+}
+
+pub fn interact_with_elo_k_factor_buttons(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor, &EloKFactorButton),
+        (Changed<Interaction>, With<EloKFactorButton>),
+    >,
+    mut elo_k_factor: ResMut<EloKFactor>,
+) {
+    for (interaction, mut background_color, elo_k_factor_button) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                elo_k_factor.0 = (elo_k_factor.0 + elo_k_factor_button.step).max(ELO_MIN_K_FACTOR);
+                println!("Elo K-factor: {}", elo_k_factor.0);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(NORMAL_BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+pub fn update_elo_k_factor_text(
+    elo_k_factor: Res<EloKFactor>,
+    mut text_query: Query<&mut Text, With<EloKFactorText>>,
+) {
+    if elo_k_factor.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = get_elo_k_factor_label(&elo_k_factor);
+        }
+    }
+}
+
+pub fn get_elo_k_factor_label(elo_k_factor: &EloKFactor) -> String {
+    format!("Elo K: {}", elo_k_factor.0)
+}
+
+/// The K-factor selector is only shown while images are ranked by Elo rating.
+pub fn show_elo_k_factor_select_if_selected(
+    rating_mode: Res<State<RatingMode>>,
+    mut elo_k_factor_select_query: Query<&mut Style, With<EloKFactorSelectComponent>>,
+) {
+    if rating_mode.is_changed() {
+        for mut style in elo_k_factor_select_query.iter_mut() {
+            style.display = get_elo_k_factor_select_display(rating_mode.get());
+        }
+    }
+}
+
+pub fn get_elo_k_factor_select_display(rating_mode: &RatingMode) -> Display {
+    match rating_mode {
+        RatingMode::Elo => Display::Flex,
+        _ => Display::None,
+    }
+}
@@ -1,15 +1,37 @@
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
+use crate::main_menu::systems::ResumedState;
+use crate::rating::RatingMode;
+use crate::resources::{EloKFactor, TopK};
 use crate::speed_select::components::*;
+use crate::speed_select::interactions::{
+    get_elo_k_factor_label, get_elo_k_factor_select_display, get_rating_mode_button_label,
+    get_top_k_label, get_top_k_select_display, get_tournament_mode_button_label,
+};
 use crate::styles::*;
 
 pub fn spawn_speed_select(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     window_query: Query<&Window, With<PrimaryWindow>>,
+    tournament_mode: Res<State<TournamentMode>>,
+    rating_mode: Res<State<RatingMode>>,
+    top_k: Res<TopK>,
+    elo_k_factor: Res<EloKFactor>,
+    resumed_state: Res<State<ResumedState>>,
 ) {
-    let speed_select_entity = build_speed_select(&mut commands, &asset_server, &window_query, true);
+    // A resumed tournament keeps the mode it was started with.
+    let speed_select_entity = build_speed_select(
+        &mut commands,
+        &asset_server,
+        &window_query,
+        tournament_mode.get(),
+        rating_mode.get(),
+        &top_k,
+        &elo_k_factor,
+        *resumed_state.get() == ResumedState::New,
+    );
 }
 
 pub fn despawn_speed_select(
@@ -25,6 +47,10 @@ pub fn build_speed_select(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     window_query: &Query<&Window, With<PrimaryWindow>>,
+    tournament_mode: &TournamentMode,
+    rating_mode: &RatingMode,
+    top_k: &TopK,
+    elo_k_factor: &EloKFactor,
     enable_speed_select: bool,
 ) -> Entity {
     let speed_select_entity = commands
@@ -163,9 +189,177 @@ pub fn build_speed_select(
                                 });
                             });
                     });
+
+                // tournament mode button
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: BUTTON_STYLE,
+                            background_color: NORMAL_BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        TournamentModeButton {},
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle {
+                                text: Text {
+                                    sections: vec![TextSection::new(
+                                        get_tournament_mode_button_label(tournament_mode),
+                                        get_button_text_style(&asset_server),
+                                    )],
+                                    justify: JustifyText::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            TournamentModeButtonText {},
+                        ));
+                    });
+
+                // top k selector
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                display: get_top_k_select_display(tournament_mode),
+                                ..NODE_BUNDLE_GAPS_ROW_STYLE
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        TopKSelectComponent {},
+                    ))
+                    .with_children(|parent| {
+                        for (label, step) in [("-10", -10), ("-1", -1)] {
+                            spawn_step_button(parent, asset_server, label, TopKButton { step });
+                        }
+
+                        parent.spawn((
+                            TextBundle {
+                                text: Text {
+                                    sections: vec![TextSection::new(
+                                        get_top_k_label(top_k),
+                                        get_button_text_style(&asset_server),
+                                    )],
+                                    justify: JustifyText::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            TopKText {},
+                        ));
+
+                        for (label, step) in [("+1", 1), ("+10", 10)] {
+                            spawn_step_button(parent, asset_server, label, TopKButton { step });
+                        }
+                    });
+
+                // rating mode button
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: BUTTON_STYLE,
+                            background_color: NORMAL_BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        RatingModeButton {},
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle {
+                                text: Text {
+                                    sections: vec![TextSection::new(
+                                        get_rating_mode_button_label(rating_mode),
+                                        get_button_text_style(&asset_server),
+                                    )],
+                                    justify: JustifyText::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            RatingModeButtonText {},
+                        ));
+                    });
+
+                // elo k-factor selector
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                display: get_elo_k_factor_select_display(rating_mode),
+                                ..NODE_BUNDLE_GAPS_ROW_STYLE
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        EloKFactorSelectComponent {},
+                    ))
+                    .with_children(|parent| {
+                        for (label, step) in [("-8", -8.0), ("-4", -4.0)] {
+                            spawn_step_button(
+                                parent,
+                                asset_server,
+                                label,
+                                EloKFactorButton { step },
+                            );
+                        }
+
+                        parent.spawn((
+                            TextBundle {
+                                text: Text {
+                                    sections: vec![TextSection::new(
+                                        get_elo_k_factor_label(elo_k_factor),
+                                        get_button_text_style(&asset_server),
+                                    )],
+                                    justify: JustifyText::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            EloKFactorText {},
+                        ));
+
+                        for (label, step) in [("+4", 4.0), ("+8", 8.0)] {
+                            spawn_step_button(
+                                parent,
+                                asset_server,
+                                label,
+                                EloKFactorButton { step },
+                            );
+                        }
+                    });
             }
         })
         .id();
 
     speed_select_entity
 }
+
+/// A small button that changes a number on the speed select screen, such as K.
+fn spawn_step_button(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: &str,
+    step_button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: SMALL_BUTTON_STYLE,
+                background_color: NORMAL_BUTTON_COLOR.into(),
+                ..default()
+            },
+            step_button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text {
+                    sections: vec![TextSection::new(label, get_button_text_style(asset_server))],
+                    justify: JustifyText::Center,
+                    ..default()
+                },
+                ..default()
+            });
+        });
+}
@@ -1,9 +1,10 @@
+use crate::rating::RatingMode;
 use crate::speed_select::interactions::*;
 use crate::speed_select::layout::*;
 use crate::AppState;
 use bevy::app::{App, Plugin, Update};
 use bevy::prelude::*;
-use components::SpeedState;
+use components::{SpeedState, TournamentMode};
 
 pub mod components;
 pub mod interactions;
@@ -14,6 +15,8 @@ pub struct SpeedSelectPlugin;
 impl Plugin for SpeedSelectPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<SpeedState>()
+            .init_state::<TournamentMode>()
+            .init_state::<RatingMode>()
             .add_systems(OnEnter(AppState::SpeedSelect), spawn_speed_select)
             .add_systems(
                 Update,
@@ -21,6 +24,16 @@ impl Plugin for SpeedSelectPlugin {
                     interact_with_begin_button,
                     interact_with_speed_select_buttons,
                     colour_the_border_if_selected,
+                    interact_with_tournament_mode_button,
+                    update_tournament_mode_button_text,
+                    interact_with_top_k_buttons,
+                    update_top_k_text,
+                    show_top_k_select_if_selected,
+                    interact_with_rating_mode_button,
+                    update_rating_mode_button_text,
+                    interact_with_elo_k_factor_buttons,
+                    update_elo_k_factor_text,
+                    show_elo_k_factor_select_if_selected,
                 )
                     .run_if(in_state(AppState::SpeedSelect)),
             )
@@ -4,6 +4,9 @@ use bevy::prelude::*;
 
 pub const NORMAL_BUTTON_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
 pub const HOVERED_BUTTON_COLOR: Color = Color::srgb(0.95, 0.95, 0.95);
+pub const STATUS_TEXT_BACKGROUND_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.8);
+pub const SELECTED_IMAGE_BORDER_COLOR: Color = Color::srgb(0.2, 0.6, 1.0);
+pub const SELECTED_IMAGE_BORDER_WIDTH: f32 = 6.0;
 
 pub const BUTTON_STYLE: Style = {
     let mut style = Style::DEFAULT;
@@ -17,6 +20,42 @@ pub const BUTTON_STYLE: Style = {
     style
 };
 
+pub const SMALL_BUTTON_STYLE: Style = {
+    let mut style = Style::DEFAULT;
+
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.width = Val::Px(80.0);
+    style.height = Val::Px(60.0);
+    style.border = UiRect::all(Val::Px(2.0));
+
+    style
+};
+
+pub const LIST_BUTTON_STYLE: Style = {
+    let mut style = Style::DEFAULT;
+
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.width = Val::Px(800.0);
+    style.height = Val::Px(48.0);
+    style.border = UiRect::all(Val::Px(2.0));
+
+    style
+};
+
+pub const TOOLBAR_BUTTON_STYLE: Style = {
+    let mut style = Style::DEFAULT;
+
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.width = Val::Px(200.0);
+    style.height = Val::Px(48.0);
+    style.border = UiRect::all(Val::Px(2.0));
+
+    style
+};
+
 pub const NODE_BUNDLE_EMPTY_COLUMN_STYLE: Style = {
     let mut style = Style::DEFAULT;
 
@@ -81,6 +120,14 @@ pub fn get_button_text_style(asset_server: &Res<AssetServer>) -> TextStyle {
     }
 }
 
+pub fn get_list_button_text_style(asset_server: &Res<AssetServer>) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/OpenSans-Regular.ttf"),
+        font_size: 24.0,
+        color: Color::BLACK,
+    }
+}
+
 pub fn get_begin_button_text_style(asset_server: &Res<AssetServer>) -> TextStyle {
     TextStyle {
         font: asset_server.load("fonts/OpenSans-SemiBold.ttf"),
@@ -14,13 +14,28 @@ pub struct ParticipantsDeque {
 }
 
 #[derive(Resource, Default, Debug)]
-pub struct ParticipantsToLoadDeque {
-    pub participants_to_load_deque: VecDeque<u64>,
+pub struct ParticipantsDequeIndices {
+    pub indices: Vec<usize>,
+    /// Identifies the displayed match, so its result is only ever recorded once.
+    pub decision_id: i64,
+}
+
+impl ParticipantsDequeIndices {
+    /// Whether a match is on screen to be decided. Once one input has decided it, any other input in the same frame finds it gone.
+    pub fn has_match_on_screen(&self) -> bool {
+        !self.indices.is_empty()
+    }
+
+    /// Whether the displayed match is large enough to pick several winners, instead of deciding on the first click.
+    pub fn allows_several_winners(&self) -> bool {
+        self.indices.len() > 2
+    }
 }
 
+/// The images picked to advance from a large match, waiting to be confirmed.
 #[derive(Resource, Default, Debug)]
-pub struct ParticipantsDequeIndices {
-    pub indices: Vec<usize>,
+pub struct SelectedWinners {
+    pub ids: Vec<u64>,
 }
 
 #[derive(Resource, Default, Debug)]
@@ -46,6 +61,21 @@ pub struct ImageComponent {
     pub id: u64,
 }
 
+/// The actions shown under a match, besides picking an image.
+#[derive(Component, Clone, Copy)]
+pub enum MatchActionButton {
+    /// Takes back the last decision of the round.
+    Undo,
+    /// Leaves the match to be decided later.
+    Skip,
+    /// Calls the match a draw.
+    Tie,
+    /// Knocks every image in the match out.
+    RejectAll,
+    /// Advances the selected images of a large match.
+    ConfirmWinners,
+}
+
 #[derive(Event)]
 pub struct TransitionToGeneratingEvent;
 
@@ -71,3 +101,18 @@ pub struct DespawnImagesEvent;
 pub struct ImageClickedEvent {
     pub id: u64,
 }
+
+#[derive(Event)]
+pub struct UndoEvent;
+
+#[derive(Event)]
+pub struct SkipEvent;
+
+#[derive(Event)]
+pub struct TieEvent;
+
+#[derive(Event)]
+pub struct RejectAllEvent;
+
+#[derive(Event)]
+pub struct ConfirmWinnersEvent;
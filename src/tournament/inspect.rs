@@ -0,0 +1,231 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use bevy::ui::FocusPolicy;
+use bevy::window::PrimaryWindow;
+
+use crate::database::{get_image_path_from_database, TournamentStore};
+use crate::styles::{get_button_text_style, STATUS_TEXT_BACKGROUND_COLOR};
+use crate::tournament::components::ImageComponent;
+use crate::tournament::thumbnails::decode_image;
+
+const INSPECT_BACKGROUND_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.9);
+const MINIMUM_ZOOM: f32 = 0.05;
+const MAXIMUM_ZOOM: f32 = 20.0;
+/// How much one line of scrolling zooms in or out.
+const ZOOM_STEP: f32 = 1.1;
+/// Touchpads scroll in pixels rather than lines.
+const PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+
+/// One participant shown on its own over the match, to judge fine detail. The match only has thumbnails, so the thumbnail is shown until the original has been decoded.
+#[derive(Component)]
+pub struct InspectView {
+    original: Option<Task<Option<Image>>>,
+    image_size: Vec2,
+    zoom: f32,
+    offset: Vec2,
+    last_cursor_position: Option<Vec2>,
+}
+
+#[derive(Component)]
+pub struct InspectedImage;
+
+/// Whether the match is on screen without an inspect view over it, so choices can be made.
+pub fn inspect_view_closed(inspect_view_query: Query<(), With<InspectView>>) -> bool {
+    inspect_view_query.is_empty()
+}
+
+/// Right-click or Space over an image of the match opens it at native resolution.
+pub fn open_inspect_view(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    image_query: Query<(&Interaction, &UiImage, &ImageComponent)>,
+    images: Res<Assets<Image>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    asset_server: Res<AssetServer>,
+    tournament_store: Res<TournamentStore>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Right) && !keyboard_input.just_pressed(KeyCode::Space)
+    {
+        return;
+    }
+
+    let Some((_, ui_image, image_component)) = image_query
+        .iter()
+        .find(|(interaction, _, _)| **interaction != Interaction::None)
+    else {
+        return;
+    };
+
+    let Some(image) = images.get(&ui_image.texture) else {
+        return;
+    };
+
+    let image_path =
+        get_image_path_from_database(&tournament_store.connection(), image_component.id)
+            .expect("Could not load the image path from the database.");
+    let original = AsyncComputeTaskPool::get().spawn(async move {
+        decode_image(&image_path)
+            .map_err(|error| {
+                println!(
+                    "Failed to decode {}: {}",
+                    image_path.to_string_lossy(),
+                    error
+                )
+            })
+            .ok()
+    });
+
+    let window = window_query.get_single().unwrap();
+    // UI pixels are logical, so one image pixel per physical pixel needs the scale factor undone
+    let zoom = 1.0 / window.scale_factor();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                background_color: INSPECT_BACKGROUND_COLOR.into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            InspectView {
+                original: Some(original),
+                image_size: image.size().as_vec2(),
+                zoom,
+                offset: Vec2::ZERO,
+                last_cursor_position: None,
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ImageBundle {
+                    style: get_inspected_image_style(
+                        image.size().as_vec2(),
+                        zoom,
+                        Vec2::ZERO,
+                        window,
+                    ),
+                    image: ui_image.clone(),
+                    ..default()
+                },
+                InspectedImage,
+            ));
+
+            parent.spawn(TextBundle {
+                text: Text::from_section(
+                    "Scroll to zoom, drag to pan, Esc to go back",
+                    get_button_text_style(&asset_server),
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.0),
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: STATUS_TEXT_BACKGROUND_COLOR.into(),
+                ..default()
+            });
+        });
+}
+
+/// Zooms with the scroll wheel and pans by dragging. Esc, Space or another right-click goes back to the match.
+pub fn update_inspect_view(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut inspect_view_query: Query<(Entity, &mut InspectView)>,
+    mut inspected_image_query: Query<(&mut Style, &mut UiImage), With<InspectedImage>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok((inspect_view_entity, mut inspect_view)) = inspect_view_query.get_single_mut() else {
+        return;
+    };
+
+    // the press that opened the view is still 'just pressed' on the frame it opens
+    let just_opened = inspect_view.is_added();
+    if !just_opened
+        && (mouse_input.just_pressed(MouseButton::Right)
+            || keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Space]))
+    {
+        commands.entity(inspect_view_entity).despawn_recursive();
+        return;
+    }
+
+    let window = window_query.get_single().unwrap();
+    let Ok((mut style, mut ui_image)) = inspected_image_query.get_single_mut() else {
+        return;
+    };
+
+    if let Some(task) = &mut inspect_view.original {
+        if let Some(original) = block_on(poll_once(task)) {
+            inspect_view.original = None;
+
+            if let Some(original) = original {
+                inspect_view.image_size = original.size().as_vec2();
+                ui_image.texture = images.add(original);
+            }
+        }
+    }
+
+    for ev in mouse_wheel_events.read() {
+        let lines = match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / PIXELS_PER_SCROLL_LINE,
+        };
+        inspect_view.zoom =
+            (inspect_view.zoom * ZOOM_STEP.powf(lines)).clamp(MINIMUM_ZOOM, MAXIMUM_ZOOM);
+    }
+
+    let cursor_position = window.cursor_position();
+    if mouse_input.pressed(MouseButton::Left) {
+        if let (Some(last), Some(current)) = (inspect_view.last_cursor_position, cursor_position) {
+            inspect_view.offset += current - last;
+        }
+        inspect_view.last_cursor_position = cursor_position;
+    } else {
+        inspect_view.last_cursor_position = None;
+    }
+
+    *style = get_inspected_image_style(
+        inspect_view.image_size,
+        inspect_view.zoom,
+        inspect_view.offset,
+        window,
+    );
+}
+
+/// The inspect view is dropped along with the match it was opened from.
+pub fn despawn_inspect_view(
+    mut commands: Commands,
+    inspect_view_query: Query<Entity, With<InspectView>>,
+) {
+    for inspect_view_entity in inspect_view_query.iter() {
+        commands.entity(inspect_view_entity).despawn_recursive();
+    }
+}
+
+/// The image, scaled by `zoom` and centred in the window before the pan `offset` is applied.
+fn get_inspected_image_style(image_size: Vec2, zoom: f32, offset: Vec2, window: &Window) -> Style {
+    let size = image_size * zoom;
+    let top_left = (Vec2::new(window.width(), window.height()) - size) / 2.0 + offset;
+
+    Style {
+        position_type: PositionType::Absolute,
+        left: Val::Px(top_left.x),
+        top: Val::Px(top_left.y),
+        width: Val::Px(size.x),
+        height: Val::Px(size.y),
+        ..default()
+    }
+}
@@ -1,6 +1,26 @@
-use crate::tournament::components::{ImageClickedEvent, ImageComponent};
+use crate::speed_select::components::TournamentMode;
+use crate::styles::{
+    HOVERED_BUTTON_COLOR, SELECTED_IMAGE_BORDER_COLOR, STATUS_TEXT_BACKGROUND_COLOR,
+};
+use crate::tournament::components::{
+    ConfirmWinnersEvent, ImageClickedEvent, ImageComponent, MatchActionButton, ParticipantsDeque,
+    ParticipantsDequeIndices, RejectAllEvent, SelectedWinners, SkipEvent, TieEvent, UndoEvent,
+};
 use bevy::prelude::*;
 
+/// The keys that pick the images of a match, in the order they are displayed.
+pub const CHOICE_KEYS: [(KeyCode, KeyCode); 9] = [
+    (KeyCode::Digit1, KeyCode::Numpad1),
+    (KeyCode::Digit2, KeyCode::Numpad2),
+    (KeyCode::Digit3, KeyCode::Numpad3),
+    (KeyCode::Digit4, KeyCode::Numpad4),
+    (KeyCode::Digit5, KeyCode::Numpad5),
+    (KeyCode::Digit6, KeyCode::Numpad6),
+    (KeyCode::Digit7, KeyCode::Numpad7),
+    (KeyCode::Digit8, KeyCode::Numpad8),
+    (KeyCode::Digit9, KeyCode::Numpad9),
+];
+
 pub fn interact_with_image_button(
     mut button_query: Query<
         (&Interaction, &ImageComponent, Entity),
@@ -23,3 +43,134 @@ pub fn interact_with_image_button(
         }
     }
 }
+
+pub fn interact_with_match_action_buttons(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor, &MatchActionButton),
+        Changed<Interaction>,
+    >,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut skip_event: EventWriter<SkipEvent>,
+    mut tie_event: EventWriter<TieEvent>,
+    mut reject_all_event: EventWriter<RejectAllEvent>,
+    mut confirm_winners_event: EventWriter<ConfirmWinnersEvent>,
+) {
+    for (interaction, mut background_color, match_action_button) in button_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                match match_action_button {
+                    MatchActionButton::Undo => {
+                        undo_event.send(UndoEvent);
+                    }
+                    MatchActionButton::Skip => {
+                        skip_event.send(SkipEvent);
+                    }
+                    MatchActionButton::Tie => {
+                        tie_event.send(TieEvent);
+                    }
+                    MatchActionButton::RejectAll => {
+                        reject_all_event.send(RejectAllEvent);
+                    }
+                    MatchActionButton::ConfirmWinners => {
+                        confirm_winners_event.send(ConfirmWinnersEvent);
+                    }
+                }
+                *background_color = BackgroundColor::from(STATUS_TEXT_BACKGROUND_COLOR);
+            }
+            Interaction::Hovered => {
+                *background_color = BackgroundColor::from(HOVERED_BUTTON_COLOR);
+            }
+            Interaction::None => {
+                *background_color = BackgroundColor::from(STATUS_TEXT_BACKGROUND_COLOR);
+            }
+        }
+    }
+}
+
+/// Ctrl+Z (Cmd+Z on macOS) or Backspace undoes the last decision.
+pub fn undo_on_keyboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut undo_event: EventWriter<UndoEvent>,
+) {
+    let modifier_pressed = keyboard_input.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]);
+
+    if keyboard_input.just_pressed(KeyCode::Backspace)
+        || (modifier_pressed && keyboard_input.just_pressed(KeyCode::KeyZ))
+    {
+        undo_event.send(UndoEvent);
+    }
+}
+
+/// Picks an image with its number key, or with Left/Right when two are shown; in large matches the number keys select winners and Enter confirms them. S skips the match, T calls it a draw in modes that allow one and X rejects every image in it.
+pub fn choose_image_on_keyboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    participants_deque_resource: Res<ParticipantsDeque>,
+    indices: Res<ParticipantsDequeIndices>,
+    tournament_mode: Res<State<TournamentMode>>,
+    mut image_clicked_event: EventWriter<ImageClickedEvent>,
+    mut skip_event: EventWriter<SkipEvent>,
+    mut tie_event: EventWriter<TieEvent>,
+    mut reject_all_event: EventWriter<RejectAllEvent>,
+    mut confirm_winners_event: EventWriter<ConfirmWinnersEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyS) {
+        skip_event.send(SkipEvent);
+        return;
+    }
+
+    if tournament_mode.get().allows_draws() && keyboard_input.just_pressed(KeyCode::KeyT) {
+        tie_event.send(TieEvent);
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyX) {
+        reject_all_event.send(RejectAllEvent);
+        return;
+    }
+
+    if keyboard_input.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        confirm_winners_event.send(ConfirmWinnersEvent);
+        return;
+    }
+
+    let chosen = if indices.indices.len() == 2 && keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        Some(0)
+    } else if indices.indices.len() == 2 && keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        Some(1)
+    } else {
+        CHOICE_KEYS.iter().position(|&(digit, numpad)| {
+            keyboard_input.just_pressed(digit) || keyboard_input.just_pressed(numpad)
+        })
+    };
+
+    let participant = chosen
+        .and_then(|chosen| indices.indices.get(chosen))
+        .and_then(|&index| participants_deque_resource.participants_deque.get(index));
+
+    if let Some(participant) = participant {
+        image_clicked_event.send(ImageClickedEvent { id: participant.id });
+    }
+}
+
+/// Outlines the images selected to advance from a large match.
+pub fn highlight_selected_winners(
+    selected_winners: Res<SelectedWinners>,
+    mut image_query: Query<(&ImageComponent, &mut BorderColor)>,
+) {
+    if !selected_winners.is_changed() {
+        return;
+    }
+
+    for (image_component, mut border_color) in image_query.iter_mut() {
+        *border_color = if selected_winners.ids.contains(&image_component.id) {
+            BorderColor(SELECTED_IMAGE_BORDER_COLOR)
+        } else {
+            BorderColor(Color::NONE)
+        };
+    }
+}
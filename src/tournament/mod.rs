@@ -1,14 +1,19 @@
 use crate::tournament::components::*;
+use crate::tournament::inspect::*;
 use crate::tournament::interactions::*;
 use crate::tournament::systems::*;
+use crate::tournament::thumbnails::*;
 use crate::AppState;
 
 use bevy::prelude::*;
 use components::ImageClickedEvent;
 
 pub mod components;
+pub mod inspect;
 pub mod interactions;
+pub mod scheduling;
 pub mod systems;
+pub mod thumbnails;
 
 pub struct TournamentPlugin;
 
@@ -22,11 +27,17 @@ impl Plugin for TournamentPlugin {
             .add_event::<DespawnImagesEvent>()
             .add_event::<ImageClickedEvent>()
             .add_event::<TransitionToResolvingEvent>()
+            .add_event::<UndoEvent>()
+            .add_event::<SkipEvent>()
+            .add_event::<TieEvent>()
+            .add_event::<RejectAllEvent>()
+            .add_event::<ConfirmWinnersEvent>()
             .init_state::<TournamentState>()
             .init_resource::<ParticipantsDeque>()
-            .init_resource::<ParticipantsToLoadDeque>()
             .init_resource::<ParticipantsDequeIndices>()
             .init_resource::<NumberOfParticipantsForMatch>()
+            .init_resource::<SelectedWinners>()
+            .init_resource::<ThumbnailTasks>()
             .add_systems(
                 Update,
                 enter_into_tournament.run_if(in_state(TournamentState::Entering)),
@@ -37,27 +48,52 @@ impl Plugin for TournamentPlugin {
             )
             .add_systems(
                 Update,
-                (
-                    check_if_image_has_loaded,
-                    find_first_two_loaded_indices,
-                    load_images,
-                )
+                find_first_two_loaded_indices
                     .run_if(in_state(AppState::Tournament))
                     .run_if(in_state(TournamentState::Loading)),
             )
             .add_systems(
                 Update,
-                (display_two_loaded_images, load_images)
+                display_two_loaded_images
                     .run_if(in_state(AppState::Tournament))
                     .run_if(in_state(TournamentState::Displaying)),
             )
             .add_systems(
                 Update,
-                (interact_with_image_button, image_clicked_decision_logic)
+                (
+                    (
+                        interact_with_image_button,
+                        interact_with_match_action_buttons,
+                        choose_image_on_keyboard_input,
+                        undo_on_keyboard_input,
+                        open_inspect_view,
+                    )
+                        .run_if(inspect_view_closed),
+                    update_inspect_view,
+                    (
+                        image_clicked_decision_logic,
+                        skip_match,
+                        record_draw_for_match,
+                        reject_all_in_match,
+                        undo_last_decision,
+                    )
+                        .chain(),
+                    highlight_selected_winners,
+                )
+                    .chain()
                     .run_if(in_state(AppState::Tournament))
                     .run_if(in_state(TournamentState::Deciding)),
             )
+            .add_systems(
+                Update,
+                (load_images, poll_thumbnail_tasks).run_if(in_state(AppState::Tournament)),
+            )
+            .add_systems(OnExit(TournamentState::Deciding), despawn_inspect_view)
             .add_systems(OnEnter(TournamentState::Resolving), resolve_deque)
+            .add_systems(
+                OnExit(AppState::Tournament),
+                (reset_tournament_state, prune_thumbnail_cache),
+            )
             .add_systems(
                 Update,
                 (
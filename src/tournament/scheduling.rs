@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::rating::{glicko_match_information, GlickoRating};
+
+// How far down the standings to look for an opponent that hasn't been played yet.
+const SWISS_PAIRING_WINDOW: usize = 10;
+// How many neighbours in the rating order are considered as opponents for each image.
+const ADAPTIVE_PAIRING_WINDOW: usize = 20;
+// The number of matches scheduled at a time. Small batches let each decision inform the next pairings.
+pub const ADAPTIVE_BATCH_SIZE: usize = 10;
+// Adaptive ranking stops once every image's rating deviation is below this.
+const ADAPTIVE_TARGET_DEVIATION: f64 = 150.0;
+
+/// Arranges participants, already sorted best first, into consecutive pairs with similar scores. Rematches are avoided when an unplayed opponent is close by in the standings.
+pub fn pair_by_score(participants: Vec<u64>, played_pairs: &HashSet<(u64, u64)>) -> Vec<u64> {
+    pair_neighbours(participants, |a, b| has_played(played_pairs, a, b))
+}
+
+/// Pairs each participant with the nearest following participant for which `already_compared` is false, falling back to the next one.
+fn pair_neighbours(
+    mut participants: Vec<u64>,
+    already_compared: impl Fn(u64, u64) -> bool,
+) -> Vec<u64> {
+    let mut paired = Vec::with_capacity(participants.len());
+
+    while !participants.is_empty() {
+        let participant = participants.remove(0);
+        paired.push(participant);
+
+        if participants.is_empty() {
+            break;
+        }
+
+        let opponent_index = participants
+            .iter()
+            .take(SWISS_PAIRING_WINDOW)
+            .position(|&opponent| !already_compared(participant, opponent))
+            .unwrap_or(0);
+        paired.push(participants.remove(opponent_index));
+    }
+
+    paired
+}
+
+/// Picks up to `batch_size` pairs that are expected to be the most informative, no image appearing twice, and returns them as consecutive pairs. Returns nothing once every rating is certain enough.
+pub fn pair_by_information(
+    mut glicko_ratings: Vec<(u64, GlickoRating)>,
+    played_pairs: &HashSet<(u64, u64)>,
+    batch_size: usize,
+) -> Vec<u64> {
+    if glicko_ratings
+        .iter()
+        .all(|(_, glicko_rating)| glicko_rating.deviation < ADAPTIVE_TARGET_DEVIATION)
+    {
+        return Vec::new();
+    }
+
+    // Only images with nearby ratings are worth comparing, so candidates come from a sliding window.
+    glicko_ratings.sort_by(|(_, a), (_, b)| b.rating.total_cmp(&a.rating));
+
+    let mut candidates = Vec::new();
+    for (i, &(participant, participant_rating)) in glicko_ratings.iter().enumerate() {
+        for &(opponent, opponent_rating) in glicko_ratings
+            .iter()
+            .skip(i + 1)
+            .take(ADAPTIVE_PAIRING_WINDOW)
+        {
+            if !has_played(played_pairs, participant, opponent) {
+                let information = glicko_match_information(participant_rating, opponent_rating);
+                candidates.push((information, participant, opponent));
+            }
+        }
+    }
+
+    candidates.sort_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+
+    let mut scheduled = HashSet::new();
+    let mut paired = Vec::with_capacity(batch_size * 2);
+
+    for (_, participant, opponent) in candidates {
+        if paired.len() >= batch_size * 2 {
+            break;
+        }
+        if scheduled.contains(&participant) || scheduled.contains(&opponent) {
+            continue;
+        }
+
+        scheduled.insert(participant);
+        scheduled.insert(opponent);
+        paired.push(participant);
+        paired.push(opponent);
+    }
+
+    paired
+}
+
+/// For each candidate, the images known to beat it, directly or through a chain of wins. The search for a candidate stops once `limit` superiors are found.
+pub fn find_known_superiors(
+    candidates: &[u64],
+    results: &[(u64, u64)],
+    limit: usize,
+) -> HashMap<u64, HashSet<u64>> {
+    let mut beaten_by: HashMap<u64, Vec<u64>> = HashMap::new();
+    for &(winner, loser) in results {
+        beaten_by.entry(loser).or_default().push(winner);
+    }
+
+    candidates
+        .iter()
+        .map(|&candidate| {
+            let mut superiors = HashSet::new();
+            let mut to_visit = vec![candidate];
+
+            while let Some(image) = to_visit.pop() {
+                if superiors.len() >= limit {
+                    break;
+                }
+                for &winner in beaten_by.get(&image).into_iter().flatten() {
+                    if winner != candidate && superiors.insert(winner) {
+                        to_visit.push(winner);
+                    }
+                }
+            }
+
+            (candidate, superiors)
+        })
+        .collect()
+}
+
+/// Arranges participants into pairs whose order isn't known yet, preferring opponents with a similar number of known superiors.
+pub fn pair_unordered(
+    mut participants: Vec<u64>,
+    known_superiors: &HashMap<u64, HashSet<u64>>,
+) -> Vec<u64> {
+    let superiors_of = |image: u64| known_superiors.get(&image);
+    let is_superior =
+        |a: u64, b: u64| superiors_of(b).is_some_and(|superiors| superiors.contains(&a));
+
+    participants.sort_by_key(|&participant| superiors_of(participant).map_or(0, HashSet::len));
+
+    pair_neighbours(participants, |a, b| is_superior(a, b) || is_superior(b, a))
+}
+
+/// The next step of an interactive sort.
+pub enum SortStep {
+    /// The user needs to compare these two images.
+    Compare(u64, u64),
+    /// Every comparison has been made; the images, best first.
+    Sorted(Vec<u64>),
+}
+
+/// Replays a binary insertion sort of `images`, answering each comparison from the match history, and stops at the first comparison that hasn't been made yet. `results` are `(winner, loser)` in the order they were played; if a pair met more than once, the latest result counts.
+pub fn next_insertion_sort_step(images: &[u64], results: &[(u64, u64)]) -> SortStep {
+    let mut winners: HashMap<(u64, u64), u64> = HashMap::new();
+    for &(winner, loser) in results {
+        winners.insert((winner.min(loser), winner.max(loser)), winner);
+    }
+
+    let mut sorted: Vec<u64> = Vec::with_capacity(images.len());
+
+    for &image in images {
+        let (mut low, mut high) = (0, sorted.len());
+
+        while low < high {
+            let middle = (low + high) / 2;
+            let pivot = sorted[middle];
+
+            match winners.get(&(image.min(pivot), image.max(pivot))) {
+                Some(&winner) if winner == image => high = middle,
+                Some(_) => low = middle + 1,
+                None => return SortStep::Compare(image, pivot),
+            }
+        }
+
+        sorted.insert(low, image);
+    }
+
+    SortStep::Sorted(sorted)
+}
+
+/// Schedules the next round of a round robin: unplayed pairs in circle-method order, each image appearing at most once. Returns nothing once every pair has been played.
+pub fn next_round_robin_round(images: &[u64], played_pairs: &HashSet<(u64, u64)>) -> Vec<u64> {
+    // the circle method: fix the first slot and rotate the rest, with an empty slot for odd counts
+    let mut slots: Vec<Option<u64>> = images.iter().map(|&image| Some(image)).collect();
+    if slots.len() % 2 == 1 {
+        slots.push(None);
+    }
+    let number_of_slots = slots.len();
+
+    let mut scheduled = HashSet::new();
+    let mut paired = Vec::new();
+
+    for _ in 0..number_of_slots.saturating_sub(1) {
+        for i in 0..number_of_slots / 2 {
+            if let (Some(a), Some(b)) = (slots[i], slots[number_of_slots - 1 - i]) {
+                if !has_played(played_pairs, a, b)
+                    && !scheduled.contains(&a)
+                    && !scheduled.contains(&b)
+                {
+                    scheduled.insert(a);
+                    scheduled.insert(b);
+                    paired.push(a);
+                    paired.push(b);
+                }
+            }
+        }
+        slots[1..].rotate_right(1);
+    }
+
+    paired
+}
+
+/// Orders images by wins. Images tied on wins are separated by their wins against each other, then by id.
+pub fn rank_by_wins(images: &[u64], results: &[(u64, u64)]) -> Vec<u64> {
+    let wins_among = |group: &HashSet<u64>| {
+        let mut wins: HashMap<u64, u64> = HashMap::new();
+        for &(winner, loser) in results {
+            if group.contains(&winner) && group.contains(&loser) {
+                *wins.entry(winner).or_default() += 1;
+            }
+        }
+        wins
+    };
+
+    let everyone: HashSet<u64> = images.iter().copied().collect();
+    let total_wins = wins_among(&everyone);
+    let total_wins_of = |image: &u64| total_wins.get(image).copied().unwrap_or(0);
+
+    let mut tied_groups: HashMap<u64, HashSet<u64>> = HashMap::new();
+    for &image in images {
+        tied_groups
+            .entry(total_wins_of(&image))
+            .or_default()
+            .insert(image);
+    }
+
+    let mut head_to_head_wins: HashMap<u64, u64> = HashMap::new();
+    for group in tied_groups.values() {
+        head_to_head_wins.extend(wins_among(group));
+    }
+    let head_to_head_wins_of = |image: &u64| head_to_head_wins.get(image).copied().unwrap_or(0);
+
+    let mut ranked = images.to_vec();
+    ranked.sort_by(|a, b| {
+        total_wins_of(b)
+            .cmp(&total_wins_of(a))
+            .then(head_to_head_wins_of(b).cmp(&head_to_head_wins_of(a)))
+            .then(a.cmp(b))
+    });
+
+    ranked
+}
+
+fn has_played(played_pairs: &HashSet<(u64, u64)>, a: u64, b: u64) -> bool {
+    played_pairs.contains(&(a.min(b), a.max(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_sort_orders_images_from_scripted_answers() {
+        // the user prefers higher ids
+        let images = [5, 3, 8, 1, 9, 2, 7];
+        let mut results = Vec::new();
+
+        let sorted = loop {
+            match next_insertion_sort_step(&images, &results) {
+                SortStep::Compare(a, b) => results.push((a.max(b), a.min(b))),
+                SortStep::Sorted(sorted) => break sorted,
+            }
+        };
+
+        assert_eq!(sorted, vec![9, 8, 7, 5, 3, 2, 1]);
+        // a binary insertion sort of 7 needs at most 1 + 2 + 2 + 3 + 3 + 3 comparisons
+        assert!(results.len() <= 14, "{} comparisons", results.len());
+    }
+
+    #[test]
+    fn insertion_sort_uses_the_latest_result_of_a_rematch() {
+        let results = [(1, 2), (2, 1)];
+
+        match next_insertion_sort_step(&[1, 2], &results) {
+            SortStep::Sorted(sorted) => assert_eq!(sorted, vec![2, 1]),
+            SortStep::Compare(a, b) => panic!("asked to compare {} and {} again", a, b),
+        }
+    }
+
+    #[test]
+    fn round_robin_plays_every_pair_exactly_once() {
+        for number_of_images in 2..=9u64 {
+            let images: Vec<u64> = (1..=number_of_images).collect();
+            let mut played_pairs = HashSet::new();
+            let mut rounds = 0;
+
+            loop {
+                let round = next_round_robin_round(&images, &played_pairs);
+                if round.is_empty() {
+                    break;
+                }
+                rounds += 1;
+
+                let scheduled: HashSet<u64> = round.iter().copied().collect();
+                assert_eq!(
+                    scheduled.len(),
+                    round.len(),
+                    "an image plays twice in a round"
+                );
+
+                for pair in round.chunks(2) {
+                    let pair = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+                    assert!(played_pairs.insert(pair), "{:?} is played twice", pair);
+                }
+            }
+
+            let n = number_of_images as usize;
+            assert_eq!(played_pairs.len(), n * (n - 1) / 2);
+            assert_eq!(rounds, if n % 2 == 0 { n - 1 } else { n });
+        }
+    }
+
+    #[test]
+    fn rank_by_wins_breaks_ties_head_to_head_then_by_id() {
+        let results = [(2, 1), (2, 3), (1, 3), (1, 4), (3, 4)];
+        assert_eq!(rank_by_wins(&[1, 2, 3, 4], &results), vec![2, 1, 3, 4]);
+
+        // 1 and 2 are tied on wins and never met
+        let results = [(1, 3), (2, 4)];
+        assert_eq!(rank_by_wins(&[2, 1, 3, 4], &results), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn known_superiors_follow_chains_of_wins() {
+        let results = [(1, 2), (2, 3), (4, 3)];
+
+        let known_superiors = find_known_superiors(&[1, 2, 3], &results, 10);
+
+        assert!(known_superiors[&1].is_empty());
+        assert_eq!(known_superiors[&2], HashSet::from([1]));
+        assert_eq!(known_superiors[&3], HashSet::from([1, 2, 4]));
+    }
+
+    #[test]
+    fn known_superiors_stop_at_the_limit_and_ignore_cycles() {
+        let results = [(1, 2), (2, 3), (3, 1), (4, 1), (5, 4)];
+
+        let known_superiors = find_known_superiors(&[1, 2, 3], &results, 2);
+
+        for candidate in [1, 2, 3] {
+            let superiors = &known_superiors[&candidate];
+            assert!(!superiors.contains(&candidate));
+            assert!(superiors.len() >= 2, "{}: {:?}", candidate, superiors);
+        }
+    }
+}
@@ -1,17 +1,28 @@
-use std::path::PathBuf;
-
-use bevy::asset::LoadState;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use rand::{random, thread_rng};
 
 use crate::database::*;
-use crate::resources::UsedMemory;
+use crate::resources::{CurrentTournament, EloKFactor, PrefetchWindow, TopK};
 use crate::speed_select::components::*;
-use crate::styles::{NODE_BUNDLE_EMPTY_COLUMN_STYLE, NODE_BUNDLE_EMPTY_ROW_STYLE};
+use crate::styles::{
+    get_button_text_style, NODE_BUNDLE_EMPTY_COLUMN_STYLE, NODE_BUNDLE_EMPTY_ROW_STYLE,
+    SELECTED_IMAGE_BORDER_WIDTH, STATUS_TEXT_BACKGROUND_COLOR, TOOLBAR_BUTTON_STYLE,
+};
 use crate::tournament::components::*;
+use crate::tournament::interactions::CHOICE_KEYS;
+use crate::tournament::scheduling::{
+    find_known_superiors, next_insertion_sort_step, next_round_robin_round, pair_by_information,
+    pair_by_score, pair_unordered, rank_by_wins, SortStep, ADAPTIVE_BATCH_SIZE,
+};
+use crate::tournament::thumbnails::{get_thumbnail_size, ThumbnailTasks};
 use crate::AppState;
+use rusqlite::Connection;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+const ROUND_ROBIN_SUGGESTED_MAXIMUM: usize = 30;
 
 /// This function gets the participants' ids for a given round from the database.
 pub fn get_participants_for_round(
@@ -19,23 +30,68 @@ pub fn get_participants_for_round(
     mut ev_finished: EventWriter<TransitionToFinishedEvent>,
     mut ev_despawn: EventWriter<DespawnImagesEvent>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
-    mut participants_to_load_resource: ResMut<ParticipantsToLoadDeque>,
     speed_state: Res<State<SpeedState>>,
+    tournament_mode: Res<State<TournamentMode>>,
     mut number_of_participants_for_match: ResMut<NumberOfParticipantsForMatch>,
+    top_k: Res<TopK>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
 ) {
-    let mut participants = get_remaining_participants().unwrap();
+    let tournament_id = current_tournament.id();
+    let mut conn = tournament_store.connection();
+
+    let participants = match tournament_mode.get() {
+        TournamentMode::Elimination => {
+            let mut participants = get_remaining_participants(&conn, tournament_id).unwrap();
+
+            let mut rng = thread_rng();
+            participants.shuffle(&mut rng);
+            participants
+        }
+        // The winners' bracket comes first and the losers' bracket after it, and the list is cut into consecutive matches, so a winners' bracket that doesn't divide evenly into matches sends its last images to play the top of the losers' bracket.
+        TournamentMode::DoubleElimination => {
+            get_remaining_participants_by_bracket(&conn, tournament_id).unwrap()
+        }
+        TournamentMode::Swiss => {
+            let round_number =
+                get_latest_round_number(&conn, tournament_id).expect("Failed to get round number");
+            let number_of_rounds = get_number_of_swiss_rounds(&conn, tournament_id)
+                .expect("Failed to get number of Swiss rounds");
+
+            if round_number > number_of_rounds {
+                Vec::new()
+            } else {
+                let participants =
+                    get_remaining_participants_by_score(&conn, tournament_id).unwrap();
+                let played_pairs =
+                    get_played_pairs(&conn, tournament_id).expect("Failed to get played pairs");
+                pair_by_score(participants, &played_pairs)
+            }
+        }
+        TournamentMode::Adaptive => {
+            let glicko_ratings =
+                get_glicko_ratings(&conn, tournament_id).expect("Failed to get Glicko-2 ratings");
+            let played_pairs =
+                get_played_pairs(&conn, tournament_id).expect("Failed to get played pairs");
+            pair_by_information(glicko_ratings, &played_pairs, ADAPTIVE_BATCH_SIZE)
+        }
+        TournamentMode::TopK => get_top_k_participants(&mut conn, tournament_id, top_k.0),
+        TournamentMode::MergeSort => get_next_sort_comparison(&mut conn, tournament_id),
+        TournamentMode::RoundRobin => get_next_round_robin_round(&mut conn, tournament_id),
+    };
 
     let num_participants = participants.len();
     calculate_number_of_images_for_match(
         num_participants,
         &speed_state,
+        tournament_mode.get(),
         &mut number_of_participants_for_match,
     );
 
     println!("Participants for round: {:?}", participants);
 
     // Tournament over
-    if participants.len() == 1 {
+    if participants.len() < 2 {
         println!("The tournament is now over.");
 
         ev_despawn.send(DespawnImagesEvent);
@@ -43,9 +99,6 @@ pub fn get_participants_for_round(
         return;
     }
 
-    let mut rng = thread_rng();
-    participants.shuffle(&mut rng);
-
     for participant in participants {
         let info = ParticipantInfo {
             id: participant,
@@ -57,119 +110,105 @@ pub fn get_participants_for_round(
         participants_deque_resource
             .participants_deque
             .push_back(info);
-        participants_to_load_resource
-            .participants_to_load_deque
-            .push_back(participant);
     }
 
     ev_loading.send(TransitionToLoadingEvent);
 }
 
-/// This function loads images one-at-a-time using Bevy's asset loader.
+/// This function keeps thumbnails loading for the next matches in the deque, up to the prefetch window, and releases the images of participants that have fallen outside it. Thumbnails still loading count against the memory budget at their expected size, and are cancelled if their participant leaves the window. The first match is always loaded, whatever its size.
 pub fn load_images(
-    asset_server: Res<AssetServer>,
-    mut participants_to_load_resource: ResMut<ParticipantsToLoadDeque>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
-    used_memory_res: Res<UsedMemory>,
+    mut thumbnail_tasks: ResMut<ThumbnailTasks>,
+    images: Res<Assets<Image>>,
+    prefetch_window: Res<PrefetchWindow>,
+    number_of_participants_for_match: Res<NumberOfParticipantsForMatch>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    tournament_store: Res<TournamentStore>,
 ) {
-    // Don't load any more images if memory used is greater than or equal to 90.0% of total memory
-    if used_memory_res.0 < 90.0 {
-        let image_id_option: Option<u64> = {
-            if let Some(image_id_1) = participants_to_load_resource
-                .participants_to_load_deque
-                .pop_front()
-            {
-                Some(image_id_1)
-            } else {
-                None
-            }
-        };
-
-        if let Some(image_id) = image_id_option {
-            let image_path = get_image_path_from_database(image_id)
-                .expect("Could not load the image path from the database.");
+    let window: &Window = window_query.get_single().unwrap();
+    let (num_rows, images_per_row) = get_match_grid(number_of_participants_for_match.0);
+    let thumbnail_size = get_thumbnail_size(window, num_rows, images_per_row);
+    // RGBA, at most `thumbnail_size` on either side
+    let expected_thumbnail_bytes = thumbnail_size as usize * thumbnail_size as usize * 4;
 
-            let mut image_handle: Option<Handle<Image>> = None;
+    let participants_per_match = number_of_participants_for_match.0.max(2);
+    let window_length = prefetch_window.matches.max(1) * participants_per_match;
+    let maximum_bytes = prefetch_window.megabytes * 1024 * 1024;
+    let mut used_bytes = 0;
+    let mut in_window = HashSet::new();
 
-            let errored = contains_non_ascii(&image_path);
+    for (position, participant) in participants_deque_resource
+        .participants_deque
+        .iter_mut()
+        .filter(|participant| !participant.errored)
+        .enumerate()
+    {
+        if position >= window_length {
+            participant.handle = None;
+            participant.loaded = false;
+            continue;
+        }
 
-            // Handle errors where the image path is incompatible with Bevy.
-            if !errored {
-                image_handle = Some(asset_server.load(image_path));
-            } else {
-                println!("{} contains non-ASCII characters and cannot be loaded by bevy. Setting to errored.", image_path.to_string_lossy().to_string());
-            }
+        in_window.insert(participant.id);
 
-            for participant in &mut participants_deque_resource.participants_deque {
-                if participant.id == image_id {
-                    participant.handle = image_handle.clone();
+        if let Some(handle) = &participant.handle {
+            used_bytes += images.get(handle).map_or(0, |image| image.data.len());
+            continue;
+        }
 
-                    if errored {
-                        participant.errored = true;
-                    }
-                }
-            }
+        if thumbnail_tasks.is_loading(participant.id) {
+            used_bytes += expected_thumbnail_bytes;
+            continue;
         }
-    } else {
-        println!(
-            "Current memory usage is {:.2}%. Not loading any more images.",
-            used_memory_res.0
-        );
-    }
-}
 
-/// This function checks to see if an image was loaded by Bevy yet. If it has failed to load (corrupt image file, etc.), it will be marked as 'errored' and will be cleaned up in the 'Resolving' state.
-pub fn check_if_image_has_loaded(
-    mut participants_deque_resource: ResMut<ParticipantsDeque>,
-    asset_server: Res<AssetServer>,
-) {
-    for participant in &mut participants_deque_resource.participants_deque {
-        if let Some(image_handle) = participant.handle.clone() {
-            let load_state = asset_server.get_load_state(&image_handle);
-
-            match load_state {
-                Some(LoadState::Loaded) => {
-                    participant.loaded = true;
-                }
-                Some(LoadState::Failed(_)) => {
-                    participant.errored = true;
-                }
-                _ => {}
-            }
+        if position >= participants_per_match && used_bytes >= maximum_bytes {
+            continue;
         }
+
+        let image_path =
+            get_image_path_from_database(&tournament_store.connection(), participant.id)
+                .expect("Could not load the image path from the database.");
+        thumbnail_tasks.spawn(participant.id, image_path, thumbnail_size);
+        used_bytes += expected_thumbnail_bytes;
     }
+
+    thumbnail_tasks.retain(|image_id| in_window.contains(&image_id));
 }
 
-/// This function finds the indices in the participants deque which have been successfully loaded.
+/// This function finds the indices of the next match in the participants deque, and displays it once all of its participants have loaded. An image that fails to load sends the round back to the scheduler, to be paired again without it.
 pub fn find_first_two_loaded_indices(
     participants_deque_resource: Res<ParticipantsDeque>,
     mut indices: ResMut<ParticipantsDequeIndices>,
     mut ev_displaying: EventWriter<TransitionToDisplayingEvent>,
+    mut ev_resolving: EventWriter<TransitionToResolvingEvent>,
     number_of_participants_for_match: Res<NumberOfParticipantsForMatch>,
 ) {
-    let mut loaded_indices = Vec::new();
-    let num_images = number_of_participants_for_match.0;
+    let participants_deque = &participants_deque_resource.participants_deque;
 
-    for (index, participant) in participants_deque_resource
-        .participants_deque
+    if participants_deque
         .iter()
-        .enumerate()
+        .any(|participant| participant.errored)
     {
-        if participant.loaded {
-            loaded_indices.push(index);
-            if loaded_indices.len() == num_images {
-                break;
-            }
-        }
+        // No decision was made, so anything recorded while resolving gets an id of its own.
+        indices.decision_id = random();
+        ev_resolving.send(TransitionToResolvingEvent);
+        return;
     }
 
-    if loaded_indices.len() == num_images {
-        for i in 0..num_images {
-            indices.indices.push(loaded_indices[i]);
-        }
+    // The last match of a round may have fewer participants than usual.
+    let num_images = participants_deque
+        .len()
+        .min(number_of_participants_for_match.0);
+
+    if num_images >= 2
+        && participants_deque
+            .iter()
+            .take(num_images)
+            .all(|participant| participant.loaded)
+    {
+        indices.indices = (0..num_images).collect();
+        indices.decision_id = random();
         ev_displaying.send(TransitionToDisplayingEvent);
-    } else {
-        println!("Less than two participants are loaded.");
     }
 }
 
@@ -182,8 +221,14 @@ pub fn display_two_loaded_images(
     window_query: Query<&Window, With<PrimaryWindow>>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
     mut indices: ResMut<ParticipantsDequeIndices>,
-    number_of_participants_for_match: Res<NumberOfParticipantsForMatch>,
+    asset_server: Res<AssetServer>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+    mut selected_winners: ResMut<SelectedWinners>,
 ) {
+    selected_winners.ids.clear();
+
     // Despawn the preexisting images if they exist
     if let Ok(both_image_components_entity) = both_image_components_query.get_single() {
         commands
@@ -194,10 +239,14 @@ pub fn display_two_loaded_images(
     let window: &Window = window_query.get_single().unwrap();
     let window_width = window.width();
     let window_height = window.height();
-    let num_images = number_of_participants_for_match.0;
-    let num_rows = if num_images >= 4 { 2 } else { 1 };
-    let images_per_row = (num_images as f32 / num_rows as f32).ceil() as usize;
+    let num_images = indices.indices.len();
+    let (num_rows, images_per_row) = get_match_grid(num_images);
     let target_width = window_width / images_per_row as f32;
+    let status_text = get_tournament_status_text(
+        &tournament_store.connection(),
+        current_tournament.id(),
+        tournament_mode.get(),
+    );
 
     commands
         .spawn((
@@ -208,6 +257,65 @@ pub fn display_two_loaded_images(
             BothImageComponents,
         ))
         .with_children(|parent| {
+            // Tournament progress, drawn over the top of the images
+            if let Some(status_text) = status_text {
+                parent.spawn(TextBundle {
+                    text: Text::from_section(status_text, get_button_text_style(&asset_server)),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(8.0),
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: STATUS_TEXT_BACKGROUND_COLOR.into(),
+                    z_index: ZIndex::Local(1),
+                    ..default()
+                });
+            }
+
+            // Match actions, drawn over the bottom of the images
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(8.0),
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    z_index: ZIndex::Local(1),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    if indices.allows_several_winners() {
+                        spawn_match_action_button(
+                            parent,
+                            &asset_server,
+                            "Confirm",
+                            MatchActionButton::ConfirmWinners,
+                        );
+                    }
+
+                    for (label, match_action_button) in [
+                        ("Undo", MatchActionButton::Undo),
+                        ("Skip", MatchActionButton::Skip),
+                        ("Tie", MatchActionButton::Tie),
+                        ("Reject all", MatchActionButton::RejectAll),
+                    ] {
+                        if matches!(match_action_button, MatchActionButton::Tie)
+                            && !tournament_mode.get().allows_draws()
+                        {
+                            continue;
+                        }
+
+                        spawn_match_action_button(
+                            parent,
+                            &asset_server,
+                            label,
+                            match_action_button,
+                        );
+                    }
+                });
+
             for row in 0..num_rows {
                 parent
                     .spawn(NodeBundle {
@@ -249,23 +357,34 @@ pub fn display_two_loaded_images(
                                             })
                                             .with_children(|parent| {
                                                 // Image
-                                                parent.spawn((
-                                                    ButtonBundle {
-                                                        style: Style {
-                                                            width: Val::Px(final_width),
-                                                            height: Val::Px(final_height),
-                                                            ..Default::default()
+                                                parent
+                                                    .spawn((
+                                                        ButtonBundle {
+                                                            style: Style {
+                                                                width: Val::Px(final_width),
+                                                                height: Val::Px(final_height),
+                                                                border: UiRect::all(Val::Px(
+                                                                    SELECTED_IMAGE_BORDER_WIDTH,
+                                                                )),
+                                                                ..Default::default()
+                                                            },
+                                                            image: UiImage::new(
+                                                                participant.handle.clone().unwrap(),
+                                                            ),
+                                                            ..default()
                                                         },
-                                                        image: UiImage::new(
-                                                            participant.handle.clone().unwrap(),
-                                                        ),
-                                                        ..default()
-                                                    },
-                                                    ImageComponent {
-                                                        index: indices.indices[idx],
-                                                        id: participant.id,
-                                                    },
-                                                ));
+                                                        ImageComponent {
+                                                            index: indices.indices[idx],
+                                                            id: participant.id,
+                                                        },
+                                                    ))
+                                                    .with_children(|parent| {
+                                                        spawn_image_key_label(
+                                                            parent,
+                                                            &asset_server,
+                                                            idx,
+                                                        );
+                                                    });
                                             });
                                     }
                                 }
@@ -278,44 +397,285 @@ pub fn display_two_loaded_images(
     ev_deciding.send(TransitionToDecidingEvent);
 }
 
-/// This function is the logic that occurs when the user clicks an image.
+/// This function is the logic that occurs when the user clicks an image. In large matches a click selects or deselects the image instead, and the selected images win once confirmed.
 pub fn image_clicked_decision_logic(
     mut ev_image_clicked: EventReader<ImageClickedEvent>,
+    mut ev_confirm_winners: EventReader<ConfirmWinnersEvent>,
     mut ev_resolving: EventWriter<TransitionToResolvingEvent>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
     mut indices: ResMut<ParticipantsDequeIndices>,
-    number_of_participants_for_match: Res<NumberOfParticipantsForMatch>,
+    mut selected_winners: ResMut<SelectedWinners>,
+    elo_k_factor: Res<EloKFactor>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
 ) {
+    let mut winners = None;
+
     for ev in ev_image_clicked.read() {
-        let id = ev.id;
+        if !indices.allows_several_winners() {
+            winners = Some(vec![ev.id]);
+            break;
+        }
+
+        if let Some(position) = selected_winners.ids.iter().position(|&id| id == ev.id) {
+            selected_winners.ids.remove(position);
+        } else {
+            selected_winners.ids.push(ev.id);
+        }
+    }
+
+    if winners.is_none() && ev_confirm_winners.read().last().is_some() {
+        if selected_winners.ids.is_empty() {
+            println!("Select the images to advance first.");
+        } else if selected_winners.ids.len() == indices.indices.len() {
+            println!("Leave at least one image unselected, or call the match a tie.");
+        } else {
+            winners = Some(selected_winners.ids.clone());
+        }
+    }
 
-        let round_number = get_latest_round_number().expect("Failed to get round number");
+    let Some(winners) = winners.filter(|_| indices.has_match_on_screen()) else {
+        return;
+    };
+
+    let tournament_id = current_tournament.id();
+    let decision_id = indices.decision_id;
+    let mut conn = tournament_store.connection();
+    let round_number =
+        get_latest_round_number(&conn, tournament_id).expect("Failed to get round number");
+
+    let losers = take_decided_participants(&mut participants_deque_resource, &mut indices)
+        .into_iter()
+        .filter(|participant| !winners.contains(participant))
+        .collect();
+
+    let match_result = MatchResult {
+        decision_id,
+        round_number,
+        winners,
+        losers,
+    };
+
+    let recorded = record_match_result(
+        &mut conn,
+        tournament_id,
+        &match_result,
+        tournament_mode.get().losses_before_elimination(),
+        elo_k_factor.0,
+    )
+    .expect("Failed to record the match result");
+
+    if !recorded {
+        println!("This match has already been recorded.");
+    }
 
-        for _ in 0..number_of_participants_for_match.0 {
-            let loser_id = participants_deque_resource
+    selected_winners.ids.clear();
+    ev_resolving.send(TransitionToResolvingEvent);
+}
+
+/// Reverts the last decision of the round and shows the same match again. Undoing the decision that closed a round takes the round back up where it was left. Modes without rounds work out the match from the history, so they only need to schedule again.
+pub fn undo_last_decision(
+    mut ev_undo: EventReader<UndoEvent>,
+    mut ev_generating: EventWriter<TransitionToGeneratingEvent>,
+    mut ev_loading: EventWriter<TransitionToLoadingEvent>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+    mut indices: ResMut<ParticipantsDequeIndices>,
+    mut number_of_participants_for_match: ResMut<NumberOfParticipantsForMatch>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    for _ in ev_undo.read() {
+        if !indices.has_match_on_screen() {
+            continue;
+        }
+
+        let undone_decision =
+            undo_last_match_result(&mut tournament_store.connection(), current_tournament.id())
+                .expect("Failed to undo the last match result");
+
+        let Some(undone_decision) = undone_decision else {
+            println!("There is nothing left to undo in this round.");
+            continue;
+        };
+
+        indices.indices.clear();
+
+        if !tournament_mode.get().uses_rounds() {
+            participants_deque_resource.participants_deque.clear();
+            ev_generating.send(TransitionToGeneratingEvent);
+            return;
+        }
+
+        // The deque already holds the next round, which hasn't started after all.
+        if undone_decision.closed_round {
+            participants_deque_resource.participants_deque.clear();
+            number_of_participants_for_match.0 = undone_decision.participants.len();
+        }
+
+        for &participant in undone_decision
+            .participants
+            .iter()
+            .chain(&undone_decision.bye)
+            .rev()
+        {
+            participants_deque_resource
                 .participants_deque
-                .pop_front()
-                .unwrap()
-                .id;
-            if loser_id != id {
-                set_loser_out(loser_id).expect("Failed to set loser");
-                increment_rating(id).expect("Failed to increment rating");
-                insert_match_into_database(round_number, id, loser_id, id)
-                    .expect("Failed to insert match");
-            }
+                .push_front(ParticipantInfo {
+                    id: participant,
+                    handle: None,
+                    loaded: false,
+                    errored: false,
+                });
+        }
+
+        ev_loading.send(TransitionToLoadingEvent);
+        return;
+    }
+}
+
+/// Puts the displayed match at the back of the round, to be decided later. Modes without rounds only ever have one match scheduled, so it is shown again.
+pub fn skip_match(
+    mut ev_skip: EventReader<SkipEvent>,
+    mut ev_resolving: EventWriter<TransitionToResolvingEvent>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+    mut indices: ResMut<ParticipantsDequeIndices>,
+) {
+    for _ in ev_skip.read() {
+        if !indices.has_match_on_screen() {
+            continue;
+        }
+
+        let mut skipped = take_match_participants(&mut participants_deque_resource, &mut indices);
+        // take_match_participants removes from the back of the deque first
+        skipped.reverse();
+        participants_deque_resource
+            .participants_deque
+            .extend(skipped);
+
+        ev_resolving.send(TransitionToResolvingEvent);
+        return;
+    }
+}
+
+/// Records the displayed match as a draw between its participants, who all stay in the tournament.
+pub fn record_draw_for_match(
+    mut ev_tie: EventReader<TieEvent>,
+    mut ev_resolving: EventWriter<TransitionToResolvingEvent>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+    mut indices: ResMut<ParticipantsDequeIndices>,
+    elo_k_factor: Res<EloKFactor>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    for _ in ev_tie.read() {
+        if !indices.has_match_on_screen() {
+            continue;
+        }
+
+        if !tournament_mode.get().allows_draws() {
+            println!("This mode needs a winner for every comparison.");
+            continue;
+        }
+
+        let tournament_id = current_tournament.id();
+        let decision_id = indices.decision_id;
+        let mut conn = tournament_store.connection();
+        let round_number =
+            get_latest_round_number(&conn, tournament_id).expect("Failed to get round number");
+
+        let participants: Vec<u64> =
+            take_decided_participants(&mut participants_deque_resource, &mut indices)
+                .into_iter()
+                .collect();
+
+        let recorded = record_draw(
+            &mut conn,
+            tournament_id,
+            decision_id,
+            round_number,
+            &participants,
+            elo_k_factor.0,
+        )
+        .expect("Failed to record the draw");
+
+        if !recorded {
+            println!("This match has already been recorded.");
+        }
+
+        ev_resolving.send(TransitionToResolvingEvent);
+        return;
+    }
+}
+
+/// Knocks every participant of the displayed match out, without any of them advancing.
+pub fn reject_all_in_match(
+    mut ev_reject_all: EventReader<RejectAllEvent>,
+    mut ev_resolving: EventWriter<TransitionToResolvingEvent>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+    mut indices: ResMut<ParticipantsDequeIndices>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
+) {
+    for _ in ev_reject_all.read() {
+        if !indices.has_match_on_screen() {
+            continue;
+        }
+
+        let tournament_id = current_tournament.id();
+        let decision_id = indices.decision_id;
+        let mut conn = tournament_store.connection();
+
+        // The finished screen needs a favourite, so the last images standing can't all be rejected.
+        let participants_still_in = get_number_of_participants_still_in(&conn, tournament_id)
+            .expect("Failed to count the participants still in");
+        if participants_still_in <= indices.indices.len() as u64 {
+            println!("These are the last images left, so one of them has to win.");
+            continue;
+        }
+
+        let round_number =
+            get_latest_round_number(&conn, tournament_id).expect("Failed to get round number");
+
+        let participants: Vec<u64> =
+            take_decided_participants(&mut participants_deque_resource, &mut indices)
+                .into_iter()
+                .collect();
+
+        let recorded = record_rejection(
+            &mut conn,
+            tournament_id,
+            decision_id,
+            round_number,
+            &participants,
+        )
+        .expect("Failed to record the rejection");
+
+        if !recorded {
+            println!("This match has already been recorded.");
         }
 
         ev_resolving.send(TransitionToResolvingEvent);
+        return;
     }
 }
 
-/// This function works to resolve the state of the tournament. It removes errored participants, and checks if a new round is needed (less than two participants left in round).
+/// This function works to resolve the state of the tournament. It removes errored participants, has the rest of the round paired again without them, and checks if a new round is needed (less than two participants left in round).
 pub fn resolve_deque(
     mut ev_generating: EventWriter<TransitionToGeneratingEvent>,
     mut ev_loading: EventWriter<TransitionToLoadingEvent>,
     mut participants_deque_resource: ResMut<ParticipantsDeque>,
     mut indices: ResMut<ParticipantsDequeIndices>,
+    tournament_mode: Res<State<TournamentMode>>,
+    current_tournament: Res<CurrentTournament>,
+    tournament_store: Res<TournamentStore>,
 ) {
+    let tournament_id = current_tournament.id();
+    let mut conn = tournament_store.connection();
+    let transaction = conn.transaction().expect("Failed to start a transaction");
+
     indices.indices.clear();
 
     let mut errored_ids = Vec::new();
@@ -327,43 +687,76 @@ pub fn resolve_deque(
         }
     }
 
-    for id in errored_ids {
+    for &id in &errored_ids {
         println!("Setting participant with id {} to out in the database", id);
-        set_loser_out(id).expect("Failed to set loser out");
+        set_loser_out(&transaction, id).expect("Failed to set loser out");
     }
 
     // Remove all errored participants
+    let any_errored = !errored_ids.is_empty();
     participants_deque_resource
         .participants_deque
         .retain(|participant| !participant.errored);
 
     let participants_left_in_round = participants_deque_resource.participants_deque.len();
 
-    if participants_left_in_round < 2 {
-        let mut round_number = get_latest_round_number().expect("Failed to get round number");
-
-        if participants_left_in_round == 1 {
-            if let Some(participant) = participants_deque_resource.participants_deque.pop_front() {
-                let sole_image = participant.id;
-                insert_match_into_database(round_number, sole_image, 0.0 as u64, sole_image)
-                    .expect("Failed to insert match");
-            }
+    if !tournament_mode.get().uses_rounds() {
+        // The next match is worked out from the history, so there is no bye or new round to record.
+        if participants_left_in_round < 2 || any_errored {
+            participants_deque_resource.participants_deque.clear();
+            ev_generating.send(TransitionToGeneratingEvent);
+        } else {
+            ev_loading.send(TransitionToLoadingEvent);
         }
+    } else if participants_left_in_round < 2 {
+        let round_number = get_latest_round_number(&transaction, tournament_id)
+            .expect("Failed to get round number");
+        let bye = participants_deque_resource
+            .participants_deque
+            .pop_front()
+            .map(|participant| participant.id);
 
-        round_number += 1;
-        insert_match_into_database(round_number, 0.0 as u64, 0.0 as u64, 0.0 as u64)
-            .expect("Failed to insert match");
+        // Part of the decision that ended the round, so undoing it reopens the round.
+        record_end_of_round(
+            &transaction,
+            tournament_id,
+            indices.decision_id,
+            round_number,
+            bye,
+        )
+        .expect("Failed to record the end of the round");
 
+        ev_generating.send(TransitionToGeneratingEvent);
+    } else if any_errored {
+        // The scheduler paired the errored images too, so the rest of the round is paired again without them.
+        participants_deque_resource.participants_deque.clear();
         ev_generating.send(TransitionToGeneratingEvent);
     } else {
         ev_loading.send(TransitionToLoadingEvent);
     }
+
+    transaction
+        .commit()
+        .expect("Failed to save the end of the round");
 }
 
 pub fn _display_current_tournament_state(tournament_state: Res<State<TournamentState>>) {
     print!("{:?} ", tournament_state);
 }
 
+/// Gets ready for the next tournament, which may be a different one from the database.
+pub fn reset_tournament_state(
+    mut next_tournament_state: ResMut<NextState<TournamentState>>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+    mut indices: ResMut<ParticipantsDequeIndices>,
+    mut thumbnail_tasks: ResMut<ThumbnailTasks>,
+) {
+    participants_deque_resource.participants_deque.clear();
+    indices.indices.clear();
+    thumbnail_tasks.clear();
+    next_tournament_state.set(TournamentState::Entering);
+}
+
 pub fn enter_into_tournament(mut ev_generating: EventWriter<TransitionToGeneratingEvent>) {
     ev_generating.send(TransitionToGeneratingEvent);
 }
@@ -441,16 +834,198 @@ pub fn despawn_images_event_listener(
     }
 }
 
-// Bevy unfortunately has problems loading file paths with non-ASCII characters
-fn contains_non_ascii(path: &PathBuf) -> bool {
-    path.to_string_lossy().chars().any(|c| !c.is_ascii())
+/// How a match of `num_images` images is laid out, as `(num_rows, images_per_row)`.
+fn get_match_grid(num_images: usize) -> (usize, usize) {
+    let num_rows = if num_images >= 4 { 2 } else { 1 };
+    let images_per_row = (num_images as f32 / num_rows as f32).ceil() as usize;
+
+    (num_rows, images_per_row)
 }
 
+/// Knocks out every image known to be beaten by at least K others, then pairs the rest. Contradictory results can put more than that over the limit, so the images with the most known superiors go first and K are always kept. Returns nothing once only K images are left, since those must be the top K.
+fn get_top_k_participants(conn: &mut Connection, tournament_id: u64, top_k: usize) -> Vec<u64> {
+    let candidates = get_images_not_out(conn, tournament_id).expect("Failed to get images");
+    let results = get_decisive_results(conn, tournament_id).expect("Failed to get match results");
+    let known_superiors = find_known_superiors(&candidates, &results, top_k);
+
+    let number_of_superiors = |image: &u64| known_superiors[image].len();
+    let mut knocked_out: Vec<u64> = candidates
+        .iter()
+        .copied()
+        .filter(|candidate| number_of_superiors(candidate) >= top_k)
+        .collect();
+    knocked_out.sort_by_key(|candidate| (Reverse(number_of_superiors(candidate)), *candidate));
+    knocked_out.truncate(candidates.len().saturating_sub(top_k));
+
+    // Part of the decision that revealed them, so undoing it brings them back.
+    knock_out_after_last_decision(conn, tournament_id, &knocked_out)
+        .expect("Failed to set losers out");
+
+    let still_in: Vec<u64> = candidates
+        .into_iter()
+        .filter(|candidate| !knocked_out.contains(candidate))
+        .collect();
+
+    if still_in.len() <= top_k {
+        return Vec::new();
+    }
+
+    let mut participants: Vec<u64> = get_remaining_participants(conn, tournament_id)
+        .unwrap()
+        .into_iter()
+        .filter(|participant| still_in.contains(participant))
+        .collect();
+
+    // When resuming at the very end of a round, start pairing everyone again.
+    if participants.len() < 2 {
+        participants = still_in;
+    }
+
+    participants.shuffle(&mut thread_rng());
+    pair_unordered(participants, &known_superiors)
+}
+
+/// The next pair the interactive sort needs compared. Once the sort is complete, the ranks are saved and nothing is returned.
+fn get_next_sort_comparison(conn: &mut Connection, tournament_id: u64) -> Vec<u64> {
+    let images = get_images_not_out(conn, tournament_id).expect("Failed to get images");
+    let results = get_decisive_results(conn, tournament_id).expect("Failed to get match results");
+
+    match next_insertion_sort_step(&images, &results) {
+        SortStep::Compare(image, pivot) => vec![image, pivot],
+        SortStep::Sorted(ordered_images) => {
+            set_ranks(conn, &ordered_images).expect("Failed to save ranks");
+            Vec::new()
+        }
+    }
+}
+
+/// The next round of unplayed pairs. Once every pair has been played, the ranks are saved and nothing is returned.
+fn get_next_round_robin_round(conn: &mut Connection, tournament_id: u64) -> Vec<u64> {
+    let images = get_images_not_out(conn, tournament_id).expect("Failed to get images");
+
+    if images.len() > ROUND_ROBIN_SUGGESTED_MAXIMUM {
+        println!(
+            "A round robin of {} images needs {} matches. It is best suited to {} images or fewer.",
+            images.len(),
+            images.len() * (images.len() - 1) / 2,
+            ROUND_ROBIN_SUGGESTED_MAXIMUM
+        );
+    }
+
+    let played_pairs = get_played_pairs(conn, tournament_id).expect("Failed to get played pairs");
+    let participants = next_round_robin_round(&images, &played_pairs);
+
+    if participants.is_empty() {
+        let results =
+            get_decisive_results(conn, tournament_id).expect("Failed to get match results");
+        set_ranks(conn, &rank_by_wins(&images, &results)).expect("Failed to save ranks");
+    }
+
+    participants
+}
+
+/// The progress line shown over a match, for tournament modes that have one.
+fn get_tournament_status_text(
+    conn: &Connection,
+    tournament_id: u64,
+    tournament_mode: &TournamentMode,
+) -> Option<String> {
+    match tournament_mode {
+        TournamentMode::DoubleElimination => {
+            let round_number =
+                get_latest_round_number(conn, tournament_id).expect("Failed to get round number");
+            let (winners_bracket, losers_bracket) =
+                get_bracket_sizes(conn, tournament_id).expect("Failed to get bracket sizes");
+
+            Some(format!(
+                "Round {} | Winners' bracket: {} | Losers' bracket: {}",
+                round_number, winners_bracket, losers_bracket
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn spawn_match_action_button(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    label: &str,
+    match_action_button: MatchActionButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: TOOLBAR_BUTTON_STYLE,
+                background_color: STATUS_TEXT_BACKGROUND_COLOR.into(),
+                ..default()
+            },
+            match_action_button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text::from_section(label, get_button_text_style(asset_server)),
+                ..default()
+            });
+        });
+}
+
+/// The number key that picks the image, shown in its top left corner.
+fn spawn_image_key_label(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>, idx: usize) {
+    if idx >= CHOICE_KEYS.len() {
+        return;
+    }
+
+    parent.spawn(TextBundle {
+        text: Text::from_section((idx + 1).to_string(), get_button_text_style(asset_server)),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            padding: UiRect::axes(Val::Px(8.0), Val::Px(0.0)),
+            ..default()
+        },
+        background_color: STATUS_TEXT_BACKGROUND_COLOR.into(),
+        ..default()
+    });
+}
+
+/// Removes the participants of a decided match from the deque and returns their ids. Their handles are dropped with them, so only the match on screen keeps their images alive until it is replaced.
+fn take_decided_participants(
+    participants_deque_resource: &mut ResMut<ParticipantsDeque>,
+    indices: &mut ResMut<ParticipantsDequeIndices>,
+) -> Vec<u64> {
+    take_match_participants(participants_deque_resource, indices)
+        .into_iter()
+        .map(|participant| participant.id)
+        .collect()
+}
+
+/// Removes the participants of the displayed match from the deque, leaving the rest of the round in order.
+fn take_match_participants(
+    participants_deque_resource: &mut ResMut<ParticipantsDeque>,
+    indices: &mut ResMut<ParticipantsDequeIndices>,
+) -> Vec<ParticipantInfo> {
+    let mut match_indices: Vec<usize> = indices.indices.drain(..).collect();
+    match_indices.sort_unstable_by(|a, b| b.cmp(a));
+
+    match_indices
+        .into_iter()
+        .filter_map(|index| participants_deque_resource.participants_deque.remove(index))
+        .collect()
+}
+
+/// Modes that pair images deliberately always show two; Fast mode only shows more at once in the others.
 fn calculate_number_of_images_for_match(
     num_participants: usize,
     speed_state: &Res<State<SpeedState>>,
+    tournament_mode: &TournamentMode,
     mut number_of_participants_for_match: &mut ResMut<NumberOfParticipantsForMatch>,
 ) {
+    if !tournament_mode.allows_large_matches() {
+        number_of_participants_for_match.0 = 2;
+        return;
+    }
+
     match speed_state.get() {
         SpeedState::Fast => match num_participants {
             0..=100 => number_of_participants_for_match.0 = 2,
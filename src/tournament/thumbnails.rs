@@ -0,0 +1,253 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageFormat};
+
+use crate::raw_previews::{is_raw_file, read_raw_preview};
+use crate::tournament::components::ParticipantsDeque;
+
+/// Thumbnail sizes are rounded up to a multiple of this, so small changes to the window still hit the cache.
+const THUMBNAIL_SIZE_STEP: u32 = 256;
+const THUMBNAIL_JPEG_QUALITY: u8 = 90;
+/// Past this size the oldest thumbnails are deleted when a tournament ends.
+const MAXIMUM_THUMBNAIL_CACHE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Thumbnails being decoded off the main thread, by participant id. Decoding them ourselves rather than through the asset server also loads file names that Bevy's asset paths can't express.
+#[derive(Resource, Default)]
+pub struct ThumbnailTasks {
+    tasks: Vec<(u64, Task<Result<Image, String>>)>,
+}
+
+impl ThumbnailTasks {
+    /// Starts loading the participant's thumbnail, no larger than `max_size` on either side.
+    pub fn spawn(&mut self, image_id: u64, image_path: PathBuf, max_size: u32) {
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            load_thumbnail(&image_path, max_size)
+                .map(to_texture)
+                .map_err(|error| format!("{}: {}", image_path.to_string_lossy(), error))
+        });
+
+        self.tasks.push((image_id, task));
+    }
+
+    pub fn is_loading(&self, image_id: u64) -> bool {
+        self.tasks.iter().any(|(id, _)| *id == image_id)
+    }
+
+    /// Drops the tasks of participants `keep` returns false for, which cancels them.
+    pub fn retain(&mut self, keep: impl Fn(u64) -> bool) {
+        self.tasks.retain(|(image_id, _)| keep(*image_id));
+    }
+
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+}
+
+/// Hands finished thumbnails to the participants waiting for them. An image that couldn't be decoded marks its participant errored, to be cleaned up in the 'Resolving' state.
+pub fn poll_thumbnail_tasks(
+    mut thumbnail_tasks: ResMut<ThumbnailTasks>,
+    mut images: ResMut<Assets<Image>>,
+    mut participants_deque_resource: ResMut<ParticipantsDeque>,
+) {
+    let mut finished = Vec::new();
+
+    thumbnail_tasks
+        .tasks
+        .retain_mut(|(image_id, task)| match block_on(poll_once(task)) {
+            Some(result) => {
+                finished.push((*image_id, result));
+                false
+            }
+            None => true,
+        });
+
+    for (image_id, result) in finished {
+        let handle = match result {
+            Ok(image) => Some(images.add(image)),
+            Err(error) => {
+                println!("Failed to decode {}. Setting to errored.", error);
+                None
+            }
+        };
+
+        for participant in &mut participants_deque_resource.participants_deque {
+            if participant.id == image_id {
+                participant.loaded = handle.is_some();
+                participant.errored = handle.is_none();
+                participant.handle = handle.clone();
+            }
+        }
+    }
+}
+
+/// The largest an image is drawn in a match laid out as `num_rows` rows of `images_per_row`, in physical pixels along its longest side.
+pub fn get_thumbnail_size(window: &Window, num_rows: usize, images_per_row: usize) -> u32 {
+    let width = window.physical_width() / images_per_row.max(1) as u32;
+    let height = window.physical_height() / num_rows.max(1) as u32;
+
+    width.max(height).div_ceil(THUMBNAIL_SIZE_STEP).max(1) * THUMBNAIL_SIZE_STEP
+}
+
+/// Decodes an image file at full resolution.
+pub fn decode_image(image_path: &Path) -> image::ImageResult<Image> {
+    open_image(image_path).map(to_texture)
+}
+
+/// Decodes an image file, or the preview embedded in a camera RAW file. Animated GIFs give their first frame.
+fn open_image(image_path: &Path) -> image::ImageResult<DynamicImage> {
+    if !is_raw_file(image_path) {
+        return image::open(image_path);
+    }
+
+    match read_raw_preview(image_path)? {
+        Some(preview) => image::load_from_memory_with_format(&preview, ImageFormat::Jpeg),
+        None => Err(ImageError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "the RAW file has no embedded JPEG preview",
+        ))),
+    }
+}
+
+/// Reads the thumbnail from the disk cache, or decodes and downsizes the original and caches the result. JPEG and PNG files that are already small enough are used as they are; anything slower to decode, such as a RAW preview, is cached whatever its size.
+fn load_thumbnail(image_path: &Path, max_size: u32) -> image::ImageResult<DynamicImage> {
+    let cache_path = get_thumbnail_cache_path(image_path, max_size);
+
+    if let Some(thumbnail) = cache_path
+        .as_ref()
+        .and_then(|cache_path| image::open(cache_path).ok())
+    {
+        return Ok(thumbnail);
+    }
+
+    let image = open_image(image_path)?;
+    let fits = image.width() <= max_size && image.height() <= max_size;
+    if fits && is_quick_to_decode(image_path) {
+        return Ok(image);
+    }
+
+    let thumbnail = if fits {
+        image
+    } else {
+        image.resize(max_size, max_size, FilterType::Triangle)
+    };
+
+    if let Some(cache_path) = cache_path {
+        if let Err(error) = save_thumbnail(&thumbnail, &cache_path) {
+            println!(
+                "Failed to cache the thumbnail of {}: {}",
+                image_path.to_string_lossy(),
+                error
+            );
+        }
+    }
+
+    Ok(thumbnail)
+}
+
+fn is_quick_to_decode(image_path: &Path) -> bool {
+    matches!(
+        ImageFormat::from_path(image_path),
+        Ok(ImageFormat::Jpeg | ImageFormat::Png)
+    )
+}
+
+/// Thumbnails are keyed by the original's path, modification time and size, so an edited photo gets a new one. The hash is FNV-1a rather than the standard library's, whose output may change between Rust releases.
+fn get_thumbnail_cache_path(image_path: &Path, max_size: u32) -> Option<PathBuf> {
+    let metadata = fs::metadata(image_path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    let hash = [
+        image_path.as_os_str().as_encoded_bytes(),
+        &modified.as_secs().to_le_bytes(),
+        &modified.subsec_nanos().to_le_bytes(),
+        &metadata.len().to_le_bytes(),
+        &max_size.to_le_bytes(),
+    ]
+    .iter()
+    .flat_map(|bytes| bytes.iter())
+    .fold(0xcbf29ce484222325_u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    Some(get_thumbnail_cache_folder()?.join(format!("{:016x}.jpg", hash)))
+}
+
+fn get_thumbnail_cache_folder() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("find_my_fav").join("thumbnails"))
+}
+
+/// Keeps the disk cache under `MAXIMUM_THUMBNAIL_CACHE_BYTES` by deleting the thumbnails that were written longest ago. Runs in the background once a tournament is over.
+pub fn prune_thumbnail_cache() {
+    let Some(cache_folder) = get_thumbnail_cache_folder() else {
+        return;
+    };
+
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let Ok(entries) = fs::read_dir(&cache_folder) else {
+                return;
+            };
+
+            let mut thumbnails: Vec<_> = entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok()?;
+                    Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+                })
+                .collect();
+
+            let mut cache_size: u64 = thumbnails.iter().map(|(_, size, _)| size).sum();
+            thumbnails.sort();
+
+            for (_, size, path) in thumbnails {
+                if cache_size <= MAXIMUM_THUMBNAIL_CACHE_BYTES {
+                    break;
+                }
+                if fs::remove_file(&path).is_ok() {
+                    cache_size -= size;
+                }
+            }
+        })
+        .detach();
+}
+
+fn save_thumbnail(thumbnail: &DynamicImage, cache_path: &Path) -> image::ImageResult<()> {
+    if let Some(cache_folder) = cache_path.parent() {
+        fs::create_dir_all(cache_folder)?;
+    }
+
+    // Written under another name first, so a half-written thumbnail is never read back.
+    let partial_path = cache_path.with_extension("partial");
+    let file = BufWriter::new(File::create(&partial_path)?);
+    JpegEncoder::new_with_quality(file, THUMBNAIL_JPEG_QUALITY)
+        .encode_image(&thumbnail.to_rgb8())?;
+    fs::rename(&partial_path, cache_path)?;
+
+    Ok(())
+}
+
+fn to_texture(image: DynamicImage) -> Image {
+    let rgba = image.into_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rgba.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}